@@ -7,16 +7,18 @@ pub fn main() {
         println!("calculating area");
         x * x
     });
+    // only observed nodes (and whatever they depend on) are kept up to date.
+    dag.observe(area.as_input());
 
     // on initial stabalization, area is calculated to be 4.
-    assert_eq!(area.observe(), 4.0);
-    length.set(3.0);
+    assert_eq!(area.observe(&dag), 4.0);
+    length.set(&mut dag, 3.0);
 
     // right after setting, dag isn't stablized yet.
-    assert_eq!(area.observe(), 4.0);
+    assert_eq!(area.observe(&dag), 4.0);
 
     dag.stablize();
-    assert_eq!(area.observe(), 9.0);
+    assert_eq!(area.observe(&dag), 9.0);
 
     println!("introducing height...");
     let height = dag.var(5.0);
@@ -24,16 +26,17 @@ pub fn main() {
         println!("calculating volume");
         x * y
     });
+    dag.observe(volume.as_input());
 
-    assert_eq!(volume.observe(), 45.0);
+    assert_eq!(volume.observe(&dag), 45.0);
 
     println!("setting height (this shouldn't trigger area calculation!)");
-    height.set(10.0);
+    height.set(&mut dag, 10.0);
     dag.stablize();
-    assert_eq!(volume.observe(), 90.0);
+    assert_eq!(volume.observe(&dag), 90.0);
 
     println!("setting length (this should trigger area calculation)");
-    length.set(2.0);
+    length.set(&mut dag, 2.0);
     dag.stablize();
-    assert_eq!(volume.observe(), 40.0);
+    assert_eq!(volume.observe(&dag), 40.0);
 }
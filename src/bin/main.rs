@@ -6,8 +6,6 @@ use incrementars::prelude::*;
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    // this actually causes stack overflow if number too big... we're using too much stack.
-    // but I guess it's not that big of a deal if we don't allocate million of nodes.
     #[arg(short, long, default_value_t = 150000)]
     linear_count: u32,
 
@@ -48,10 +46,11 @@ pub fn perf_test() {
         for _ in 0..count {
             map = dag.map(map.as_input(), |x| x + 1);
         }
+        dag.observe(map.as_input());
 
         // time it
         let start = std::time::Instant::now();
-        var.set(10);
+        var.set(&mut dag, 10);
         dag.stablize();
         let end = std::time::Instant::now();
         Metrics {
@@ -68,18 +67,21 @@ pub fn perf_test() {
         let mut dag = Incrementars::new();
         let var = dag.var(0);
         let map0 = dag.map(var.as_input(), |x| x + 1);
-        let mut queues: Vec<Box<Map1<i32, i32>>> = vec![map0.as_input()];
+        let mut queues: Vec<Input<i32>> = vec![map0.as_input()];
         for _ in 0..layers / 2 {
             let head = queues.pop().unwrap();
-            let out1 = dag.map(head.clone(), |x| x + 1);
+            let out1 = dag.map(head, |x| x + 1);
             let out2 = dag.map(head, |x| x + 2);
             queues.push(out1.as_input());
             queues.push(out2.as_input());
             count += 2;
         }
+        queues.iter().for_each(|leaf| {
+            dag.observe(*leaf);
+        });
 
         let start = std::time::Instant::now();
-        var.set(10);
+        var.set(&mut dag, 10);
         dag.stablize();
         let end = std::time::Instant::now();
         Metrics {
@@ -95,10 +97,7 @@ pub fn perf_test() {
         let mut count = vars_num;
         let mut dag = Incrementars::new();
 
-        let vars = (0..vars_num)
-            .into_iter()
-            .map(|i| dag.var(i))
-            .collect::<Vec<_>>();
+        let vars = (0..vars_num).map(|i| dag.var(i)).collect::<Vec<_>>();
         let mut queue = vars
             .chunks(2)
             .filter_map(|vars| match vars.len() {
@@ -121,8 +120,14 @@ pub fn perf_test() {
                 None => continue,
             }
         }
+        queue.iter().for_each(|root| {
+            dag.observe(root.as_input());
+        });
 
-        vars.into_iter().for_each(|n| n.set(n.observe() + 1));
+        vars.into_iter().for_each(|n| {
+            let next = n.observe(&dag) + 1;
+            n.set(&mut dag, next);
+        });
 
         std::thread::sleep(Duration::from_secs(1));
         let start = std::time::Instant::now();
@@ -144,18 +149,21 @@ pub fn perf_test() {
         let mut dag = Incrementars::new();
         let var = dag.var(0);
         let map0 = dag.map(var.as_input(), |x| x);
-        let mut queues: Vec<Box<Map1<i32, i32>>> = vec![map0.as_input()];
+        let mut queues: Vec<Input<i32>> = vec![map0.as_input()];
         for _ in 0..layers / 2 {
             let head = queues.pop().unwrap();
-            let out1 = dag.map(head.clone(), |x| x);
+            let out1 = dag.map(head, |x| x);
             let out2 = dag.map(head, |x| x);
             queues.push(out1.as_input());
             queues.push(out2.as_input());
             count += 2;
         }
+        queues.iter().for_each(|leaf| {
+            dag.observe(*leaf);
+        });
         let start = std::time::Instant::now();
         for _ in 0..iter {
-            var.set(10);
+            var.set(&mut dag, 10);
             dag.stablize();
         }
         let end = std::time::Instant::now();
@@ -1,74 +1,90 @@
-use std::ops::Deref;
-use std::{cell::RefCell, rc::Rc};
+use std::marker::PhantomData;
 
-use super::traits::{Node, Observable, StablizationCallback};
+use super::id::{Input, NodeId};
+use super::scope::Scope;
+use super::traits::{sync_dyn, Node, NodeValue, Observable, StablizationCallback};
+
+type BindFn<I, O> = Box<sync_dyn!(Fn(I, &mut Scope) -> Input<O>)>;
 
 pub struct _Bind1<I, O> {
-    pub id: usize,
-    pub depth: i32,
-    pub value: Box<dyn Observable<O>>,
-    pub input: Box<dyn Observable<I>>,
-    pub f: Box<dyn Fn(I) -> Box<dyn Observable<O>>>,
+    pub id: NodeId,
+    // `None` until `f` first runs, if the bind was built on a failed input.
+    pub value: Option<Input<O>>,
+    pub input: Input<I>,
+    pub f: BindFn<I, O>,
+    // stabilization in which `input` had changed the last time `f` ran.
+    pub input_changed_at: u64,
 }
 
-impl<I, O> Node for _Bind1<I, O> {
-    fn id(&self) -> usize {
-        self.id
+impl<I: 'static, O: 'static> _Bind1<I, O> {
+    fn input_ids(&self) -> impl Iterator<Item = NodeId> {
+        [self.input.id()]
+            .into_iter()
+            .chain(self.value.map(|value| value.id()))
     }
+}
 
-    fn stablize(&mut self) -> Vec<StablizationCallback> {
-        let new_value = (self.f)(self.input.observe());
-        if *self.value == *new_value {
-            return vec![];
+impl<I: Clone + 'static, O: 'static> Node for _Bind1<I, O> {
+    fn stablize(&mut self, scope: &mut Scope) -> Option<StablizationCallback> {
+        let input_changed_at = scope.changed_at(self.input.id());
+        if input_changed_at == self.input_changed_at && self.value.is_some() {
+            // only the picked node changed; no need to pick again.
+            return Some(StablizationCallback::ValueChanged);
         }
-        let old_id = self.value.id();
-        let new_id = new_value.id();
-
-        self.value = new_value;
-        vec![
-            StablizationCallback::ValueChanged,
-            StablizationCallback::DependenciesUpdated {
-                from: vec![old_id],
-                to: vec![new_id],
-            },
-        ]
+        self.input_changed_at = input_changed_at;
+        let input = scope.read::<I>(self.input.id()).clone();
+        let new_value = scope.activate(self.id, |scope| (self.f)(input, scope));
+        let old_value = self.value.replace(new_value);
+        if old_value.is_some_and(|old_value| old_value.id() == new_value.id()) {
+            // same node picked, but we may have fired because that node changed.
+            return Some(StablizationCallback::ValueChanged);
+        }
+        Some(StablizationCallback::DependenciesUpdated {
+            from: old_value.iter().map(|old_value| old_value.id()).collect(),
+            to: vec![new_value.id()],
+        })
     }
-    fn depth(&self) -> i32 {
-        self.depth
+    fn inputs(&self) -> Vec<NodeId> {
+        self.input_ids().collect()
     }
-
-    fn adjust_depth(&mut self, new_depth: i32) {
-        self.depth = new_depth;
+    fn reads_from(&self, inputs: &[NodeId]) -> bool {
+        self.input_ids().eq(inputs.iter().copied())
+    }
+    fn value(&self) -> NodeValue<'_> {
+        NodeValue::At(self.value.unwrap().id())
+    }
+    fn changes_graph(&self) -> bool {
+        true
+    }
+    fn drop_pick(&mut self) {
+        self.value = None;
     }
 }
 
 pub struct Bind1<I, O> {
-    pub node: Rc<RefCell<_Bind1<I, O>>>,
+    id: NodeId,
+    _types: PhantomData<fn(I) -> O>,
 }
 
-impl<I, O: Clone> Observable<O> for Bind1<I, O> {
-    fn id(&self) -> usize {
-        self.node.deref().borrow().id
-    }
-    fn observe(&self) -> O {
-        let borrowed = self.node.deref().borrow();
-        borrowed.value.observe()
-    }
-    fn depth(&self) -> i32 {
-        self.node.deref().borrow().depth
+impl<I: 'static, O: 'static> Observable<O> for Bind1<I, O> {
+    fn id(&self) -> NodeId {
+        self.id
     }
 }
 
 impl<I, O> Clone for Bind1<I, O> {
     fn clone(&self) -> Self {
-        Self {
-            node: self.node.clone(),
-        }
+        *self
     }
 }
 
+impl<I, O> Copy for Bind1<I, O> {}
+
 impl<I, O> Bind1<I, O> {
-    pub fn as_input(&self) -> Box<Bind1<I, O>> {
-        Box::new(self.clone())
+    pub(super) fn new(id: NodeId) -> Self {
+        Self {
+            id,
+            _types: PhantomData,
+        }
     }
 }
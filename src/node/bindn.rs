@@ -0,0 +1,148 @@
+use std::marker::PhantomData;
+
+use super::id::{Input, NodeId};
+use super::traits::{sync_dyn, Node, NodeValue, Observable, SendSync, StablizationCallback};
+use super::{Incrementars, Scope};
+
+/// Generates an n-ary bind node following the same layout as `_Bind1`: the internal node, its
+/// typed handle, and the constructors on [`Incrementars`] and [`Scope`].
+macro_rules! bind_n {
+    ($node:ident, $handle:ident, $method:ident, $(($input:ident, $ty:ident)),+) => {
+        pub struct $node<$($ty,)+ O> {
+            pub id: NodeId,
+            // `None` until `f` first runs, if the bind was built on a failed input.
+            pub value: Option<Input<O>>,
+            $(pub $input: Input<$ty>,)+
+            #[allow(clippy::type_complexity)]
+            pub f: Box<sync_dyn!(Fn($($ty,)+ &mut Scope) -> Input<O>)>,
+            // latest stabilization in which any input had changed the last time `f` ran.
+            pub inputs_changed_at: u64,
+        }
+
+        impl<$($ty: 'static,)+ O: 'static> $node<$($ty,)+ O> {
+            fn input_ids(&self) -> impl Iterator<Item = NodeId> {
+                [$(self.$input.id()),+]
+                    .into_iter()
+                    .chain(self.value.map(|value| value.id()))
+            }
+        }
+
+        impl<$($ty: Clone + 'static,)+ O: 'static> Node for $node<$($ty,)+ O> {
+            fn stablize(&mut self, scope: &mut Scope) -> Option<StablizationCallback> {
+                let inputs_changed_at = [$(scope.changed_at(self.$input.id())),+]
+                    .into_iter()
+                    .max()
+                    .unwrap();
+                if inputs_changed_at == self.inputs_changed_at && self.value.is_some() {
+                    // only the picked node changed; no need to pick again.
+                    return Some(StablizationCallback::ValueChanged);
+                }
+                self.inputs_changed_at = inputs_changed_at;
+                $(let $input = scope.read::<$ty>(self.$input.id()).clone();)+
+                let new_value = scope.activate(self.id, |scope| (self.f)($($input,)+ scope));
+                let old_value = self.value.replace(new_value);
+                if old_value.is_some_and(|old_value| old_value.id() == new_value.id()) {
+                    return Some(StablizationCallback::ValueChanged);
+                }
+                Some(StablizationCallback::DependenciesUpdated {
+                    from: old_value.iter().map(|old_value| old_value.id()).collect(),
+                    to: vec![new_value.id()],
+                })
+            }
+            fn inputs(&self) -> Vec<NodeId> {
+                self.input_ids().collect()
+            }
+            fn reads_from(&self, inputs: &[NodeId]) -> bool {
+                self.input_ids().eq(inputs.iter().copied())
+            }
+            fn value(&self) -> NodeValue<'_> {
+                NodeValue::At(self.value.unwrap().id())
+            }
+            fn changes_graph(&self) -> bool {
+                true
+            }
+            fn drop_pick(&mut self) {
+                self.value = None;
+            }
+        }
+
+        pub struct $handle<$($ty,)+ O> {
+            id: NodeId,
+            _types: PhantomData<fn($($ty),+) -> O>,
+        }
+
+        impl<$($ty: 'static,)+ O: 'static> Observable<O> for $handle<$($ty,)+ O> {
+            fn id(&self) -> NodeId {
+                self.id
+            }
+        }
+
+        impl<$($ty,)+ O> Clone for $handle<$($ty,)+ O> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<$($ty,)+ O> Copy for $handle<$($ty,)+ O> {}
+
+        impl Incrementars {
+            /// Like [`Incrementars::bind`], but picks based on several inputs at once. `f` runs
+            /// again whenever any of them changes.
+            pub fn $method<$($ty: Clone + 'static,)+ O: 'static>(
+                &mut self,
+                $($input: impl Observable<$ty>,)+
+                f: impl Fn($($ty,)+ &mut Scope) -> Input<O> + SendSync + 'static,
+            ) -> $handle<$($ty,)+ O> {
+                let id = self.next_id();
+                $(let $input = $input.as_input();)+
+                let inputs_changed_at = [$($input.id()),+]
+                    .into_iter()
+                    .map(|input_id| self.nodes[input_id.index].changed_at)
+                    .max()
+                    .unwrap();
+                let value = match ($(self.read_input::<$ty>($input.id()).cloned(),)+) {
+                    ($(Some($input),)+) => {
+                        Some(Scope::new(self).activate(id, |scope| (f)($($input,)+ scope)))
+                    }
+                    _ => None,
+                };
+                self.add_node(
+                    id,
+                    Box::new($node {
+                        id,
+                        value,
+                        $($input,)+
+                        f: Box::new(f),
+                        inputs_changed_at,
+                    }),
+                );
+                $handle {
+                    id,
+                    _types: PhantomData,
+                }
+            }
+        }
+
+        impl<'g> Scope<'g> {
+            pub fn $method<$($ty: Clone + 'static,)+ O: 'static>(
+                &mut self,
+                $($input: impl Observable<$ty>,)+
+                f: impl Fn($($ty,)+ &mut Scope) -> Input<O> + SendSync + 'static,
+            ) -> $handle<$($ty,)+ O> {
+                self.dag_mut().$method($($input,)+ f)
+            }
+        }
+    };
+}
+
+bind_n!(_Bind2, Bind2, bind2, (input1, I1), (input2, I2));
+bind_n!(_Bind3, Bind3, bind3, (input1, I1), (input2, I2), (input3, I3));
+bind_n!(
+    _Bind4,
+    Bind4,
+    bind4,
+    (input1, I1),
+    (input2, I2),
+    (input3, I3),
+    (input4, I4)
+);
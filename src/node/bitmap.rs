@@ -5,7 +5,7 @@ pub struct Bitmap {
 
 impl Bitmap {
     pub fn new(size: usize) -> Self {
-        let num_elements = (size + 63) / 64; // Number of u64 elements needed
+        let num_elements = size.div_ceil(64); // Number of u64 elements needed
         Bitmap {
             bits: vec![0; num_elements],
         }
@@ -13,9 +13,10 @@ impl Bitmap {
 
     pub fn insert(&mut self, value: usize) {
         let (index, bit) = (value / 64, value % 64);
-        if index < self.bits.len() {
-            self.bits[index] |= 1 << bit;
+        if index >= self.bits.len() {
+            self.bits.resize(index + 1, 0);
         }
+        self.bits[index] |= 1 << bit;
     }
 
     pub fn contains(&self, value: &usize) -> bool {
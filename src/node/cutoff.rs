@@ -0,0 +1,53 @@
+use super::traits::{sync_dyn, SendSync};
+
+type CompareFn<T> = Box<sync_dyn!(Fn(&T, &T) -> bool)>;
+
+/// Decides whether a recomputed value is different enough from the previous one to be propagated
+/// to dependents.
+pub struct Cutoff<T> {
+    f: Option<CompareFn<T>>,
+}
+
+impl<T> Cutoff<T> {
+    /// Always propagate, even if the new value is identical to the old one.
+    pub fn never() -> Self {
+        Self { f: None }
+    }
+
+    /// Stop propagation whenever `f(old, new)` returns true.
+    pub fn custom(f: impl Fn(&T, &T) -> bool + SendSync + 'static) -> Self {
+        Self {
+            f: Some(Box::new(f)),
+        }
+    }
+
+    pub fn is_never(&self) -> bool {
+        self.f.is_none()
+    }
+
+    pub fn should_cutoff(&self, old: &T, new: &T) -> bool {
+        match &self.f {
+            Some(f) => (f)(old, new),
+            None => false,
+        }
+    }
+
+    /// Like [`Cutoff::should_cutoff`], for a node that may not have a value yet. A first value
+    /// always propagates.
+    pub(super) fn should_cutoff_from(&self, old: &Option<T>, new: &T) -> bool {
+        old.as_ref().is_some_and(|old| self.should_cutoff(old, new))
+    }
+}
+
+impl<T: PartialEq> Cutoff<T> {
+    /// Stop propagation when the new value equals the old one. This is the default for map nodes.
+    pub fn partial_eq() -> Self {
+        Self::custom(|old: &T, new: &T| old == new)
+    }
+}
+
+impl<T: PartialEq> Default for Cutoff<T> {
+    fn default() -> Self {
+        Self::partial_eq()
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+
+use super::id::NodeId;
+
+/// Errors reported by the fallible graph operations, such as [`Incrementars::try_stablize`].
+///
+/// [`Incrementars::try_stablize`]: super::Incrementars::try_stablize
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncrError {
+    /// The node has been removed from the graph.
+    Removed(NodeId),
+    /// A bind picked a node that depends on it. Holds the nodes on the cycle, starting with the
+    /// bind and ending with the node it picked.
+    Cycle(Vec<NodeId>),
+    /// The function of a fallible node such as a [`TryMap1`] failed. Nodes reading from it fail
+    /// with the same error until it recovers.
+    ///
+    /// [`TryMap1`]: super::TryMap1
+    Failed { node: NodeId, message: String },
+    /// The function of a node panicked while panics were being caught, see
+    /// [`Incrementars::set_catch_panics`]. Propagates like [`IncrError::Failed`].
+    ///
+    /// [`Incrementars::set_catch_panics`]: super::Incrementars::set_catch_panics
+    Panicked { node: NodeId, message: String },
+    /// The node is not observed, and a node it reads from, directly or further up, changed while
+    /// nothing was looking. Its value is out of date until it is observed again.
+    Stale(NodeId),
+    /// A `SharedIncrementars` was used from a node function or another call running while the
+    /// same thread holds it exclusively, to stabilize or build nodes.
+    Reentrant,
+}
+
+impl fmt::Display for IncrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncrError::Removed(id) => write!(f, "node {:?} has been removed from the graph", id),
+            IncrError::Cycle(nodes) => {
+                write!(
+                    f,
+                    "node {:?} reading from {:?} would create a cycle: ",
+                    nodes[0],
+                    nodes[nodes.len() - 1]
+                )?;
+                nodes.iter().try_for_each(|id| write!(f, "{:?} -> ", id))?;
+                write!(f, "{:?}", nodes[0])
+            }
+            IncrError::Failed { node, message } => write!(f, "node {:?} failed: {}", node, message),
+            IncrError::Panicked { node, message } => {
+                write!(f, "node {:?} panicked: {}", node, message)
+            }
+            IncrError::Stale(id) => {
+                write!(
+                    f,
+                    "node {:?} is not observed, so its value is out of date",
+                    id
+                )
+            }
+            IncrError::Reentrant => {
+                f.write_str("cannot use the graph while this thread stabilizes or builds it")
+            }
+        }
+    }
+}
+
+impl std::error::Error for IncrError {}
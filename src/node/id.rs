@@ -0,0 +1,40 @@
+use std::marker::PhantomData;
+
+use super::traits::Observable;
+
+/// Identifies a node in an [`Incrementars`](super::Incrementars) graph. Slots of removed nodes get
+/// reused with a new generation, so ids held on to after a removal never match a live node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId {
+    pub index: usize,
+    pub generation: u32,
+}
+
+/// Typed id of a node whose value is a `T`, used to wire it into other nodes.
+pub struct Input<T> {
+    id: NodeId,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Input<T> {
+    pub(super) fn new(id: NodeId) -> Self {
+        Self {
+            id,
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Input<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Input<T> {}
+
+impl<T: 'static> Observable<T> for Input<T> {
+    fn id(&self) -> NodeId {
+        self.id
+    }
+}
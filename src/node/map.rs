@@ -1,60 +1,65 @@
-use std::ops::Deref;
-use std::{cell::RefCell, rc::Rc};
+use std::marker::PhantomData;
 
-use super::traits::{Node, Observable, StablizationCallback};
+use super::cutoff::Cutoff;
+use super::id::{Input, NodeId};
+use super::scope::Scope;
+use super::traits::{sync_dyn, Node, NodeValue, Observable, SendSync, StablizationCallback};
+use super::Incrementars;
 
 pub struct _Map1<I, O> {
-    pub id: usize,
-    pub depth: i32,
-    pub value: O,
-    pub input: Box<dyn Observable<I>>,
-    pub f: fn(I) -> O,
+    // `None` until an input first holds a value, if the node was built on a failed one.
+    pub value: Option<O>,
+    pub input: Input<I>,
+    pub f: Box<sync_dyn!(Fn(&I) -> O)>,
+    pub cutoff: Cutoff<O>,
 }
 
-impl<I, O> Node for _Map1<I, O> {
-    fn id(&self) -> usize {
-        self.id
-    }
-
-    fn stablize(&mut self) -> Vec<StablizationCallback> {
-        self.value = (self.f)(self.input.observe());
-        vec![StablizationCallback::ValueChanged]
+impl<I: 'static, O: SendSync + 'static> Node for _Map1<I, O> {
+    fn stablize(&mut self, scope: &mut Scope) -> Option<StablizationCallback> {
+        let new_value = (self.f)(scope.read(self.input.id()));
+        if self.cutoff.should_cutoff_from(&self.value, &new_value) {
+            return None;
+        }
+        self.value = Some(new_value);
+        Some(StablizationCallback::ValueChanged)
     }
-    fn depth(&self) -> i32 {
-        self.depth
+    fn inputs(&self) -> Vec<NodeId> {
+        vec![self.input.id()]
     }
-    fn adjust_depth(&mut self, new_depth: i32) {
-        self.depth = new_depth;
+    fn value(&self) -> NodeValue<'_> {
+        NodeValue::Here(self.value.as_ref().unwrap())
     }
 }
 
 pub struct Map1<I, O> {
-    pub node: Rc<RefCell<_Map1<I, O>>>,
+    id: NodeId,
+    _types: PhantomData<fn(I) -> O>,
 }
 
-impl<I, O: Clone> Observable<O> for Map1<I, O> {
-    fn id(&self) -> usize {
-        self.node.deref().borrow().id
-    }
-    fn observe(&self) -> O {
-        let borrowed = self.node.deref().borrow();
-        borrowed.value.clone()
-    }
-    fn depth(&self) -> i32 {
-        self.node.deref().borrow().depth
+impl<I: 'static, O: 'static> Observable<O> for Map1<I, O> {
+    fn id(&self) -> NodeId {
+        self.id
     }
 }
 
 impl<I, O> Clone for Map1<I, O> {
     fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I, O> Copy for Map1<I, O> {}
+
+impl<I: 'static, O: SendSync + 'static> Map1<I, O> {
+    pub(super) fn new(id: NodeId) -> Self {
         Self {
-            node: self.node.clone(),
+            id,
+            _types: PhantomData,
         }
     }
-}
 
-impl<I, O> Map1<I, O> {
-    pub fn as_input(&self) -> Box<Map1<I, O>> {
-        Box::new(self.clone())
+    /// Replaces the cutoff used to decide whether a recomputed value propagates to dependents.
+    pub fn set_cutoff(&self, dag: &mut Incrementars, cutoff: Cutoff<O>) {
+        dag.node_mut::<_Map1<I, O>>(self.id).cutoff = cutoff;
     }
 }
@@ -1,61 +1,68 @@
-use std::ops::Deref;
-use std::{cell::RefCell, rc::Rc};
+use std::marker::PhantomData;
 
-use super::traits::{Node, Observable, StablizationCallback};
+use super::cutoff::Cutoff;
+use super::id::{Input, NodeId};
+use super::scope::Scope;
+use super::traits::{sync_dyn, Node, NodeValue, Observable, SendSync, StablizationCallback};
+use super::Incrementars;
+
+type Map2Fn<I1, I2, O> = Box<sync_dyn!(Fn(&I1, &I2) -> O)>;
 
 pub struct _Map2<I1, I2, O> {
-    pub id: usize,
-    pub depth: i32,
-    pub value: O,
-    pub input1: Box<dyn Observable<I1>>,
-    pub input2: Box<dyn Observable<I2>>,
-    pub f: fn(I1, I2) -> O,
+    // `None` until both inputs first hold a value, if the node was built on a failed one.
+    pub value: Option<O>,
+    pub input1: Input<I1>,
+    pub input2: Input<I2>,
+    pub f: Map2Fn<I1, I2, O>,
+    pub cutoff: Cutoff<O>,
 }
 
-impl<I1, I2, O> Node for _Map2<I1, I2, O> {
-    fn id(&self) -> usize {
-        self.id
+impl<I1: 'static, I2: 'static, O: SendSync + 'static> Node for _Map2<I1, I2, O> {
+    fn stablize(&mut self, scope: &mut Scope) -> Option<StablizationCallback> {
+        let new_value = (self.f)(scope.read(self.input1.id()), scope.read(self.input2.id()));
+        if self.cutoff.should_cutoff_from(&self.value, &new_value) {
+            return None;
+        }
+        self.value = Some(new_value);
+        Some(StablizationCallback::ValueChanged)
     }
-    fn stablize(&mut self) -> Vec<StablizationCallback> {
-        self.value = (self.f)(self.input1.observe(), self.input2.observe());
-        vec![StablizationCallback::ValueChanged]
+    fn inputs(&self) -> Vec<NodeId> {
+        vec![self.input1.id(), self.input2.id()]
     }
-    fn depth(&self) -> i32 {
-        self.depth
-    }
-
-    fn adjust_depth(&mut self, new_depth: i32) {
-        self.depth = new_depth;
+    fn value(&self) -> NodeValue<'_> {
+        NodeValue::Here(self.value.as_ref().unwrap())
     }
 }
 
 pub struct Map2<I1, I2, O> {
-    pub node: Rc<RefCell<_Map2<I1, I2, O>>>,
+    id: NodeId,
+    _types: PhantomData<fn(I1, I2) -> O>,
 }
 
-impl<I1, I2, O: Clone> Observable<O> for Map2<I1, I2, O> {
-    fn id(&self) -> usize {
-        self.node.deref().borrow().id
-    }
-    fn observe(&self) -> O {
-        let borrowed = self.node.deref().borrow();
-        borrowed.value.clone()
-    }
-    fn depth(&self) -> i32 {
-        self.node.deref().borrow().depth
+impl<I1: 'static, I2: 'static, O: 'static> Observable<O> for Map2<I1, I2, O> {
+    fn id(&self) -> NodeId {
+        self.id
     }
 }
 
 impl<I1, I2, O> Clone for Map2<I1, I2, O> {
     fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I1, I2, O> Copy for Map2<I1, I2, O> {}
+
+impl<I1: 'static, I2: 'static, O: SendSync + 'static> Map2<I1, I2, O> {
+    pub(super) fn new(id: NodeId) -> Self {
         Self {
-            node: self.node.clone(),
+            id,
+            _types: PhantomData,
         }
     }
-}
 
-impl<I1, I2, O> Map2<I1, I2, O> {
-    pub fn as_input(&self) -> Box<Map2<I1, I2, O>> {
-        Box::new(self.clone())
+    /// Replaces the cutoff used to decide whether a recomputed value propagates to dependents.
+    pub fn set_cutoff(&self, dag: &mut Incrementars, cutoff: Cutoff<O>) {
+        dag.node_mut::<_Map2<I1, I2, O>>(self.id).cutoff = cutoff;
     }
 }
@@ -0,0 +1,176 @@
+use std::marker::PhantomData;
+
+use super::cutoff::Cutoff;
+use super::id::{Input, NodeId};
+use super::traits::{sync_dyn, Node, NodeValue, Observable, SendSync, StablizationCallback};
+use super::{Incrementars, Scope};
+
+/// Generates an n-ary map node following the same layout as `_Map2`: the internal node, its typed
+/// handle, and the constructor on [`Incrementars`].
+macro_rules! map_n {
+    (
+        $node:ident,
+        $handle:ident,
+        $method:ident,
+        $with_cutoff:ident,
+        $(($input:ident, $ty:ident)),+
+    ) => {
+        pub struct $node<$($ty,)+ O> {
+            // `None` until every input first holds a value, if the node was built on a failed one.
+            pub value: Option<O>,
+            $(pub $input: Input<$ty>,)+
+            pub f: Box<sync_dyn!(Fn($($ty),+) -> O)>,
+            pub cutoff: Cutoff<O>,
+        }
+
+        impl<$($ty: Clone + 'static,)+ O: SendSync + 'static> Node for $node<$($ty,)+ O> {
+            fn stablize(&mut self, scope: &mut Scope) -> Option<StablizationCallback> {
+                let new_value = (self.f)($(scope.read::<$ty>(self.$input.id()).clone()),+);
+                if self.cutoff.should_cutoff_from(&self.value, &new_value) {
+                    return None;
+                }
+                self.value = Some(new_value);
+                Some(StablizationCallback::ValueChanged)
+            }
+            fn inputs(&self) -> Vec<NodeId> {
+                vec![$(self.$input.id()),+]
+            }
+            fn value(&self) -> NodeValue<'_> {
+                NodeValue::Here(self.value.as_ref().unwrap())
+            }
+        }
+
+        pub struct $handle<$($ty,)+ O> {
+            id: NodeId,
+            _types: PhantomData<fn($($ty),+) -> O>,
+        }
+
+        impl<$($ty: 'static,)+ O: 'static> Observable<O> for $handle<$($ty,)+ O> {
+            fn id(&self) -> NodeId {
+                self.id
+            }
+        }
+
+        impl<$($ty,)+ O> Clone for $handle<$($ty,)+ O> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<$($ty,)+ O> Copy for $handle<$($ty,)+ O> {}
+
+        impl<$($ty: Clone + 'static,)+ O: SendSync + 'static> $handle<$($ty,)+ O> {
+            /// Replaces the cutoff used to decide whether a recomputed value propagates to
+            /// dependents.
+            pub fn set_cutoff(&self, dag: &mut Incrementars, cutoff: Cutoff<O>) {
+                dag.node_mut::<$node<$($ty,)+ O>>(self.id).cutoff = cutoff;
+            }
+        }
+
+        impl Incrementars {
+            /// Creates a node that applies `f` to all of its inputs at once. Recomputed values
+            /// equal to the previous one do not propagate.
+            #[allow(clippy::too_many_arguments)]
+            pub fn $method<$($ty: Clone + 'static,)+ O: PartialEq + SendSync + 'static>(
+                &mut self,
+                $($input: impl Observable<$ty>,)+
+                f: impl Fn($($ty),+) -> O + SendSync + 'static,
+            ) -> $handle<$($ty,)+ O> {
+                self.$with_cutoff($($input,)+ Cutoff::default(), f)
+            }
+
+            /// Like the constructor above, but starts out with `cutoff` instead of the default
+            /// one, so `O` need not be `PartialEq`.
+            #[allow(clippy::too_many_arguments)]
+            pub fn $with_cutoff<$($ty: Clone + 'static,)+ O: SendSync + 'static>(
+                &mut self,
+                $($input: impl Observable<$ty>,)+
+                cutoff: Cutoff<O>,
+                f: impl Fn($($ty),+) -> O + SendSync + 'static,
+            ) -> $handle<$($ty,)+ O> {
+                let id = self.next_id();
+                let value = match ($(self.read_input::<$ty>($input.id()).cloned(),)+) {
+                    ($(Some($input),)+) => Some((f)($($input),+)),
+                    _ => None,
+                };
+                self.add_node(
+                    id,
+                    Box::new($node {
+                        value,
+                        $($input: $input.as_input(),)+
+                        f: Box::new(f),
+                        cutoff,
+                    }),
+                );
+                $handle {
+                    id,
+                    _types: PhantomData,
+                }
+            }
+        }
+
+        impl<'g> Scope<'g> {
+            #[allow(clippy::too_many_arguments)]
+            pub fn $method<$($ty: Clone + 'static,)+ O: PartialEq + SendSync + 'static>(
+                &mut self,
+                $($input: impl Observable<$ty>,)+
+                f: impl Fn($($ty),+) -> O + SendSync + 'static,
+            ) -> $handle<$($ty,)+ O> {
+                self.dag_mut().$method($($input,)+ f)
+            }
+
+            #[allow(clippy::too_many_arguments)]
+            pub fn $with_cutoff<$($ty: Clone + 'static,)+ O: SendSync + 'static>(
+                &mut self,
+                $($input: impl Observable<$ty>,)+
+                cutoff: Cutoff<O>,
+                f: impl Fn($($ty),+) -> O + SendSync + 'static,
+            ) -> $handle<$($ty,)+ O> {
+                self.dag_mut().$with_cutoff($($input,)+ cutoff, f)
+            }
+        }
+    };
+}
+
+map_n!(
+    _Map3,
+    Map3,
+    map3,
+    map3_with_cutoff,
+    (input1, I1),
+    (input2, I2),
+    (input3, I3)
+);
+map_n!(
+    _Map4,
+    Map4,
+    map4,
+    map4_with_cutoff,
+    (input1, I1),
+    (input2, I2),
+    (input3, I3),
+    (input4, I4)
+);
+map_n!(
+    _Map5,
+    Map5,
+    map5,
+    map5_with_cutoff,
+    (input1, I1),
+    (input2, I2),
+    (input3, I3),
+    (input4, I4),
+    (input5, I5)
+);
+map_n!(
+    _Map6,
+    Map6,
+    map6,
+    map6_with_cutoff,
+    (input1, I1),
+    (input2, I2),
+    (input3, I3),
+    (input4, I4),
+    (input5, I5),
+    (input6, I6)
+);
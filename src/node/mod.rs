@@ -1,442 +1,1555 @@
-use std::cmp::min;
-use std::collections::{BinaryHeap, HashMap};
-use std::ops::Deref;
-use std::{cell::RefCell, rc::Rc};
+use std::any::Any;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 
 use bitmap::Bitmap;
+use recompute_heap::RecomputeHeap;
 
-use traits::StablizationCallback;
-
-use self::traits::MaybeDirty;
+use traits::{NodeValue, StablizationCallback};
 mod bind;
+mod bindn;
 mod bitmap;
+mod cutoff;
+mod error;
+mod id;
 mod map;
 mod map2;
+mod mapn;
+mod observer;
+mod recompute_heap;
+mod scope;
+#[cfg(feature = "sync")]
+mod shared;
 mod traits;
+mod try_map;
+mod try_map2;
 mod var;
+#[cfg(feature = "sync")]
+pub use self::shared::SharedIncrementars;
 pub use self::{
     bind::{Bind1, _Bind1},
+    bindn::{Bind2, Bind3, Bind4, _Bind2, _Bind3, _Bind4},
+    cutoff::Cutoff,
+    error::IncrError,
+    id::{Input, NodeId},
     map::{Map1, _Map1},
     map2::{Map2, _Map2},
-    traits::{Node, Observable},
+    mapn::{Map3, Map4, Map5, Map6, _Map3, _Map4, _Map5, _Map6},
+    observer::{Observer, Update, _Observer},
+    scope::Scope,
+    traits::{Node, Observable, SendSync},
+    try_map::{TryMap1, _TryMap1},
+    try_map2::{TryMap2, _TryMap2},
     var::{Var, _Var},
 };
 
-pub struct Incrementars<'a> {
-    nodes: Vec<Rc<RefCell<dyn Node + 'a>>>,
-    id_counter: usize,
+struct Slot {
+    generation: u32,
+    // `None` while the slot is free, reserved for a node that is still being built, or while its
+    // node recomputes.
+    node: Option<Box<dyn Node>>,
+    // whether the slot holds a node that belongs to the graph.
+    live: bool,
+    // how many observers and necessary dependents the node has. Only nodes with a non-zero count
+    // take part in stabilization.
+    necessary: u32,
+    // an input changed while the node was unnecessary.
+    stale: bool,
+    // stabilization in which the node's value last changed.
+    changed_at: u64,
+    // stabilization in which the node was last queued for recomputing.
+    queued_at: u64,
+    // step (see `Incrementars::steps`) in which the node was last built or recomputed, and in
+    // which its value last changed.
+    ran_at_step: u64,
+    changed_at_step: u64,
+    height: usize,
+    // nodes this node reads from.
+    inputs: Vec<NodeId>,
+    // nodes that read from this node.
+    dependents: Vec<NodeId>,
+    // why the node has no up to date value, if it failed or reads from a node that did.
+    error: Option<IncrError>,
+}
+
+/// A graph of incremental computations. The graph owns every node; the handles it gives out are
+/// small `Copy` ids that go through the graph to read or change a node.
+pub struct Incrementars {
+    nodes: Vec<Slot>,
+    // indices of slots available for reuse.
+    free: Vec<usize>,
+    // slots freed during the current stabilization. The recompute heap may still link their
+    // indices, so they only become available once it ends.
+    released: Vec<usize>,
+    stabilizing: bool,
+
+    // vars set since the last stabilization.
+    dirty: Vec<NodeId>,
+
+    // observers whose handlers run at the end of the current (or next) stabilization.
+    pending_observers: Vec<NodeId>,
+    // observers of removed nodes, told at the end of the next stabilization.
+    invalidated: Vec<Box<dyn Node>>,
+
+    // stale nodes that became necessary and must recompute on the next stabilization.
+    recompute: Vec<NodeId>,
+    // nodes to recompute in the current stabilization. Kept around so its buckets are reused.
+    queue: RecomputeHeap,
+    // number of stabilizations run so far.
+    stabilization: u64,
+    // number of times a node was built or recomputed so far. Orders those events across
+    // stabilizations, so `check_invariants` can tell whether a node ran after its inputs changed.
+    steps: u64,
+
+    // bind whose function is currently building nodes, if any.
+    current_scope: Option<NodeId>,
+    // key is bind id, value is list of nodes built by its latest activation.
+    scopes: HashMap<NodeId, Vec<NodeId>>,
+    // nodes from finished activations, waiting to be invalidated.
+    retired: Vec<NodeId>,
+
+    // whether a panicking node fails instead of unwinding out of the stabilization.
+    catch_panics: bool,
+    // buffer behind `Running`, kept around so recomputing a node does not allocate.
+    running: Vec<TakenNode>,
+}
+
+// spawning a thread costs about as much as recomputing a few hundred simple nodes, so
+// `stablize_parallel` keeps smaller levels on the calling thread.
+#[cfg(feature = "sync")]
+const MIN_NODES_PER_THREAD: usize = 512;
 
-    inputs: Vec<Box<dyn MaybeDirty + 'a>>,
-    // key is node id, value is list of node ids that depend on the node.
-    dependencies: HashMap<usize, Vec<usize>>,
+impl Default for Incrementars {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<'a: 'static> Incrementars<'a> {
+impl Incrementars {
     pub fn new() -> Self {
         Self {
             nodes: vec![],
-            id_counter: 0,
-            inputs: vec![],
-            dependencies: HashMap::new(),
+            free: vec![],
+            released: vec![],
+            stabilizing: false,
+            dirty: vec![],
+            pending_observers: vec![],
+            invalidated: vec![],
+            recompute: vec![],
+            queue: RecomputeHeap::new(),
+            stabilization: 0,
+            steps: 0,
+            current_scope: None,
+            scopes: HashMap::new(),
+            retired: vec![],
+            catch_panics: false,
+            running: vec![],
         }
     }
 
-    pub fn var<T: Copy + 'a>(&mut self, value: T) -> Var<T> {
-        let id = self.id_counter;
-        self.id_counter += 1;
-        // max height for dag is 1000.
-        let node = Rc::new(RefCell::new(_Var::new(id, 1_000, value)));
-        self.nodes.push(node.clone());
-        self.inputs.push(Box::new(Var { node: node.clone() }));
-        Var { node }
+    /// With `catch` set, a node whose function panics while stabilizing fails with
+    /// [`IncrError::Panicked`] instead, taking its dependents along, and the rest of the graph
+    /// still stabilizes. A panicking observer handler is skipped, and the other handlers still
+    /// run. Off by default. The panic hook still reports each panic.
+    pub fn set_catch_panics(&mut self, catch: bool) {
+        self.catch_panics = catch;
     }
 
-    pub fn map<I: 'a, O: 'a>(
-        &mut self,
-        input: Box<dyn Observable<I>>,
-        f: fn(I) -> O,
-    ) -> Map1<I, O> {
-        let id = self.id_counter;
-        self.id_counter += 1;
-        let input_id = input.id();
-        match self.dependencies.get_mut(&input_id) {
-            Some(input_deps) => input_deps.push(id),
-            None => {
-                self.dependencies.insert(input_id, vec![id]);
+    /// Whether `id` refers to a node that is still part of the graph.
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.nodes
+            .get(id.index)
+            .is_some_and(|slot| slot.generation == id.generation && slot.live)
+    }
+
+    fn check_contains(&self, id: NodeId) -> Result<(), IncrError> {
+        if self.contains(id) {
+            Ok(())
+        } else {
+            Err(IncrError::Removed(id))
+        }
+    }
+
+    fn assert_contains(&self, id: NodeId) {
+        if let Err(err) = self.check_contains(id) {
+            panic!("{}", err);
+        }
+    }
+
+    fn node(&self, id: NodeId) -> &dyn Node {
+        self.assert_contains(id);
+        match &self.nodes[id.index].node {
+            Some(node) => node.as_ref(),
+            None => panic!("node {:?} is being recomputed", id),
+        }
+    }
+
+    /// The node behind `id`, as the concrete type its handle knows it to be.
+    fn node_ref<N: Node>(&self, id: NodeId) -> &N {
+        let node: &dyn Any = self.node(id);
+        node.downcast_ref().unwrap()
+    }
+
+    fn node_mut<N: Node>(&mut self, id: NodeId) -> &mut N {
+        self.assert_contains(id);
+        let node: &mut dyn Any = match &mut self.nodes[id.index].node {
+            Some(node) => node.as_mut(),
+            None => panic!("node {:?} is being recomputed", id),
+        };
+        node.downcast_mut().unwrap()
+    }
+
+    /// Current value of `id`, following binds to the node they picked.
+    fn read<T: 'static>(&self, id: NodeId) -> &T {
+        match self.try_read(id) {
+            Ok(value) => value,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    fn try_read<T: 'static>(&self, id: NodeId) -> Result<&T, IncrError> {
+        self.check_contains(id)?;
+        self.check_current(id)?;
+        self.read_held(id)
+    }
+
+    /// The value `id` holds, even if it is out of date.
+    fn read_held<T: 'static>(&self, id: NodeId) -> Result<&T, IncrError> {
+        let mut id = id;
+        loop {
+            self.check_contains(id)?;
+            if let Some(err) = &self.nodes[id.index].error {
+                return Err(err.clone());
+            }
+            match self.node(id).value() {
+                NodeValue::Here(value) => return Ok(value.downcast_ref().unwrap()),
+                NodeValue::At(next) => id = next,
             }
         }
-        let node = Rc::new(RefCell::new(_Map1 {
-            id,
-            depth: input.depth() - 1,
-            value: (f)(input.observe()),
-            input,
-            f,
-        }));
-        self.nodes.push(node.clone());
-        Map1 { node }
     }
 
-    pub fn map2<I1: 'a, I2: 'a, O: 'a>(
-        &mut self,
-        input1: Box<dyn Observable<I1>>,
-        input2: Box<dyn Observable<I2>>,
-        f: fn(I1, I2) -> O,
-    ) -> Map2<I1, I2, O> {
-        let id = self.id_counter;
-        self.id_counter += 1;
-        for input_id in [input1.id(), input2.id()] {
-            match self.dependencies.get_mut(&input_id) {
-                Some(input_deps) => input_deps.push(id),
-                None => {
-                    self.dependencies.insert(input_id, vec![id]);
-                }
+    /// Current value of `id` for a node being built on it, or `None` if it failed. Nodes built on
+    /// a failed input start out failed themselves, and compute once it recovers.
+    fn read_input<T: 'static>(&self, id: NodeId) -> Option<&T> {
+        self.assert_contains(id);
+        // an out of date input is fine: observing the new node brings both up to date.
+        self.read_held(id).ok()
+    }
+
+    /// Fails if `id` is not observed and may have missed a change. Unobserved nodes are marked
+    /// stale when an input changes, but the nodes above them are not.
+    fn check_current(&self, id: NodeId) -> Result<(), IncrError> {
+        if self.nodes[id.index].necessary > 0 {
+            return Ok(());
+        }
+        let mut seen = Bitmap::new(self.nodes.len());
+        let mut stack = vec![id];
+        while let Some(next) = stack.pop() {
+            let slot = &self.nodes[next.index];
+            if slot.stale {
+                return Err(IncrError::Stale(id));
             }
+            // necessary nodes are up to date, and mark their unnecessary dependents stale.
+            slot.inputs.iter().for_each(|input| {
+                if self.nodes[input.index].necessary == 0 && !seen.contains(&input.index) {
+                    seen.insert(input.index);
+                    stack.push(*input);
+                }
+            });
         }
-        let node = Rc::new(RefCell::new(_Map2 {
-            id,
-            depth: min(input1.depth(), input2.depth()) - 1,
-            value: (f)(input1.observe(), input2.observe()),
-            input1,
-            input2,
-            f,
-        }));
-        self.nodes.push(node.clone());
-        Map2 { node }
+        Ok(())
     }
 
-    pub fn bind<I: 'a, O: 'a>(
-        &mut self,
-        input: Box<dyn Observable<I>>,
-        f: Box<impl Fn(I) -> Box<dyn Observable<O>> + 'a>,
-    ) -> Bind1<I, O> {
-        let id = self.id_counter;
-        self.id_counter += 1;
-        let input_id = input.id();
-        let value = (f)(input.observe());
-        let value_id = value.id();
-        let depth = min(input.depth(), value.depth()) - 1;
-        let node = Rc::new(RefCell::new(_Bind1 {
-            id,
-            depth,
-            value,
-            input,
-            f,
-        }));
-        [input_id, value_id]
+    fn error(&self, id: NodeId) -> Option<&IncrError> {
+        self.assert_contains(id);
+        self.nodes[id.index].error.as_ref()
+    }
+
+    /// The error of the first failed input of `id`, if any.
+    fn failed_input(&self, id: NodeId) -> Option<IncrError> {
+        self.nodes[id.index]
+            .inputs
             .iter()
-            .for_each(|x| match self.dependencies.get_mut(x) {
-                Some(deps) => deps.push(id),
-                None => {
-                    self.dependencies.insert(*x, vec![id]);
+            .find_map(|input| self.nodes[input.index].error.clone())
+    }
+
+    fn height(&self, id: NodeId) -> usize {
+        self.assert_contains(id);
+        self.nodes[id.index].height
+    }
+
+    /// Reserves a slot for a node about to be built, registering it with the running bind scope.
+    fn next_id(&mut self) -> NodeId {
+        let id = self.alloc_id();
+        if let Some(owner) = self.current_scope {
+            self.scopes.entry(owner).or_default().push(id);
+        }
+        id
+    }
+
+    fn alloc_id(&mut self) -> NodeId {
+        match self.free.pop() {
+            Some(index) => NodeId {
+                index,
+                generation: self.nodes[index].generation,
+            },
+            None => {
+                self.nodes.push(Slot {
+                    generation: 0,
+                    node: None,
+                    live: false,
+                    necessary: 0,
+                    stale: false,
+                    changed_at: 0,
+                    queued_at: 0,
+                    ran_at_step: 0,
+                    changed_at_step: 0,
+                    height: 0,
+                    inputs: vec![],
+                    dependents: vec![],
+                    error: None,
+                });
+                NodeId {
+                    index: self.nodes.len() - 1,
+                    generation: 0,
                 }
-            });
-        self.nodes.push(node.clone());
-        Bind1 { node }
+            }
+        }
     }
 
-    pub fn stablize(&mut self) {
-        let dirty_inputs = self.inputs.iter().filter(|x| x.is_dirty());
-
-        let mut queue = dirty_inputs
-            .map(|x| x.id())
-            .map(|id| self.nodes.get(id).unwrap().deref().borrow())
-            .map(|node| (node.depth(), node.id()))
-            .collect::<BinaryHeap<(i32, usize)>>();
-
-        let mut visited = Bitmap::new(self.nodes.len());
-
-        while let Some((_h, head_id)) = queue.pop() {
-            let node = &self.nodes[head_id];
-            let res = node.deref().borrow_mut().stablize();
-            res.into_iter().for_each(|cb| match cb {
-                StablizationCallback::ValueChanged => match self.dependencies.get(&head_id) {
-                    Some(dependent_ids) => {
-                        dependent_ids.iter().for_each(|id| {
-                            // because pseudoheight guarantees that all nodes must fire *after* all
-                            // of its dependencies fire, node needs to only be fired once. Skip if
-                            // we have already visited this node.
-                            if !visited.contains(id) {
-                                visited.insert(*id);
-                                let depth = self.nodes[*id].deref().borrow().depth();
-                                queue.push((depth, *id));
-                            }
-                        })
-                    }
-                    None => {}
-                },
-                StablizationCallback::DependenciesUpdated { from, to } => {
-                    from.iter()
-                        .for_each(|id| match self.dependencies.get_mut(id) {
-                            Some(deps) => {
-                                deps.retain(|x| *x != head_id);
-                            }
-                            None => {}
-                        });
-                    to.iter()
-                        .for_each(|id| match self.dependencies.get_mut(id) {
-                            Some(deps) => {
-                                deps.push(head_id);
-                            }
-                            None => {
-                                self.dependencies.insert(*id, vec![head_id]);
-                            }
-                        });
+    /// Puts a built node into its reserved slot, one above its highest input, and registers it
+    /// as a dependent of each of its inputs.
+    fn add_node(&mut self, id: NodeId, node: Box<dyn Node>) {
+        let inputs = node.inputs();
+        inputs.iter().for_each(|input| self.assert_contains(*input));
+        let height = inputs
+            .iter()
+            .map(|input| self.nodes[input.index].height + 1)
+            .max()
+            .unwrap_or(0);
+        inputs
+            .iter()
+            .for_each(|input| self.nodes[input.index].dependents.push(id));
+        self.steps += 1;
+        let slot = &mut self.nodes[id.index];
+        slot.node = Some(node);
+        slot.live = true;
+        slot.ran_at_step = self.steps;
+        slot.height = height;
+        slot.inputs = inputs;
+        self.nodes[id.index].error = self.failed_input(id);
+        self.queue.reserve(height, id);
+    }
 
-                    let adjust_start_id = node.deref().borrow().id();
-                    let mut adjust_queue = vec![adjust_start_id];
-
-                    while let Some(node_id) = adjust_queue.pop() {
-                        let min_upstream_depth = self
-                            .dependencies
-                            .iter()
-                            .filter_map(|(other, deps)| {
-                                if deps.contains(&node_id) {
-                                    Some(self.nodes.get(*other).unwrap().deref().borrow().depth())
-                                } else {
-                                    None
-                                }
-                            })
-                            .min();
-
-                        if let Some(raw_depth) = min_upstream_depth {
-                            let old_depth =
-                                self.nodes.get(node_id).unwrap().deref().borrow().depth();
-                            let new_depth = raw_depth - 1;
-                            if new_depth < old_depth {
-                                self.nodes
-                                    .get(node_id)
-                                    .unwrap()
-                                    .borrow_mut()
-                                    .adjust_depth(new_depth);
-                                let this_node_id = node.deref().borrow().id();
-                                if let Some(dependencies) = self.dependencies.get(&this_node_id) {
-                                    adjust_queue.extend(dependencies);
-                                }
-                            }
-                        }
-                    }
+    /// Gives `id` one more reason to be necessary, making its inputs necessary as well if it just
+    /// became so.
+    fn add_necessary(&mut self, id: NodeId) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let slot = &mut self.nodes[id.index];
+            slot.necessary += 1;
+            if slot.necessary == 1 {
+                if slot.stale {
+                    self.recompute.push(id);
                 }
-            })
+                stack.extend(&slot.inputs);
+            }
         }
     }
 
-    pub fn print(&self) {
-        self.dependencies.iter().for_each(|(id, deps)| {
-            println!("dep | {:?} depends on {}", deps, id);
-        });
-        self.nodes.iter().for_each(|node| {
-            let bor = node.deref().borrow();
-            println!("node | {} @ {}", bor.id(), bor.depth());
-        })
+    /// Takes away one reason for `id` to be necessary, releasing its inputs if it has none left.
+    fn remove_necessary(&mut self, id: NodeId) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let slot = &mut self.nodes[id.index];
+            slot.necessary -= 1;
+            if slot.necessary == 0 {
+                stack.extend(&slot.inputs);
+            }
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use lazy_static::lazy_static;
+    pub fn var<T: Clone + SendSync + 'static>(&mut self, value: T) -> Var<T> {
+        let id = self.next_id();
+        self.add_node(id, Box::new(_Var::new(value)));
+        Var::new(id)
+    }
 
-    use super::*;
-    #[test]
-    fn var_instantiation() {
-        let mut dag = Incrementars::new();
-        let var = dag.var(0);
-        var.set(10);
-        assert_eq!(var.observe(), 10);
+    /// Creates a node that applies `f` to its input. Recomputed values equal to the previous one do
+    /// not propagate; see [`Map1::set_cutoff`] to change that.
+    pub fn map<I: Clone + 'static, O: PartialEq + SendSync + 'static>(
+        &mut self,
+        input: impl Observable<I>,
+        f: impl Fn(I) -> O + SendSync + 'static,
+    ) -> Map1<I, O> {
+        self.map_with_cutoff(input, Cutoff::default(), f)
     }
 
-    #[test]
-    fn map_instantiation() {
-        let mut dag = Incrementars::new();
-        let var = dag.var(0);
-        let map = dag.map(Box::new(var), |x| x + 1);
-        assert_eq!(map.observe(), 1);
+    /// Like [`Incrementars::map`], but starts out with `cutoff` instead of the default one, so
+    /// `O` need not be `PartialEq`.
+    pub fn map_with_cutoff<I: Clone + 'static, O: SendSync + 'static>(
+        &mut self,
+        input: impl Observable<I>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(I) -> O + SendSync + 'static,
+    ) -> Map1<I, O> {
+        self.map_ref_with_cutoff(input, cutoff, move |input: &I| (f)(input.clone()))
     }
 
-    #[test]
-    fn bifurcate() {
-        let mut dag = Incrementars::new();
-        let var = dag.var(0);
-        let map = dag.map(var.as_input(), |x| x + 1);
-        let map2 = dag.map(var.as_input(), |x| x + 1);
-        assert_eq!(map.observe(), 1);
-        assert_eq!(map2.observe(), 1);
+    /// Like [`Incrementars::map`], but `f` borrows its input instead of taking a clone of it.
+    pub fn map_ref<I: 'static, O: PartialEq + SendSync + 'static>(
+        &mut self,
+        input: impl Observable<I>,
+        f: impl Fn(&I) -> O + SendSync + 'static,
+    ) -> Map1<I, O> {
+        self.map_ref_with_cutoff(input, Cutoff::default(), f)
+    }
 
-        var.set(10);
-        assert_eq!(map.observe(), 1);
-        dag.stablize();
-        assert_eq!(map.observe(), 11);
-        assert_eq!(map2.observe(), 11);
+    /// Like [`Incrementars::map_ref`], starting out with `cutoff`.
+    pub fn map_ref_with_cutoff<I: 'static, O: SendSync + 'static>(
+        &mut self,
+        input: impl Observable<I>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(&I) -> O + SendSync + 'static,
+    ) -> Map1<I, O> {
+        let id = self.next_id();
+        let value = self.read_input(input.id()).map(&f);
+        self.add_node(
+            id,
+            Box::new(_Map1 {
+                value,
+                input: input.as_input(),
+                f: Box::new(f),
+                cutoff,
+            }),
+        );
+        Map1::new(id)
     }
 
-    #[test]
-    fn test_map2() {
-        let mut dag = Incrementars::new();
-        let var1 = dag.var(50);
-        let var2 = dag.var(" dollars");
-        let map2 = dag.map2(var1.as_input(), var2.as_input(), |x, y| x.to_string() + y);
-        assert_eq!(map2.observe(), "50 dollars");
+    /// Creates a node that applies `f` to its two inputs. Recomputed values equal to the previous
+    /// one do not propagate; see [`Map2::set_cutoff`] to change that.
+    pub fn map2<I1: Clone + 'static, I2: Clone + 'static, O: PartialEq + SendSync + 'static>(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        f: impl Fn(I1, I2) -> O + SendSync + 'static,
+    ) -> Map2<I1, I2, O> {
+        self.map2_with_cutoff(input1, input2, Cutoff::default(), f)
     }
 
-    #[test]
-    fn test_combinatoric() {
-        let mut dag = Incrementars::new();
-        let var1 = dag.var(50);
-        let plus_one = |x| x + 1;
-        let var21 = dag.map(var1.as_input(), plus_one.clone());
-        let var22 = dag.map(var21.as_input(), plus_one.clone());
-        let var23 = dag.map(var22.as_input(), plus_one.clone());
-        let var31 = dag.map(var1.as_input(), plus_one.clone());
-        let rejoin = dag.map2(var31.as_input(), var23.as_input(), |x, y| x + y);
+    /// Like [`Incrementars::map2`], starting out with `cutoff`; see
+    /// [`Incrementars::map_with_cutoff`].
+    pub fn map2_with_cutoff<I1: Clone + 'static, I2: Clone + 'static, O: SendSync + 'static>(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(I1, I2) -> O + SendSync + 'static,
+    ) -> Map2<I1, I2, O> {
+        self.map2_ref_with_cutoff(input1, input2, cutoff, move |input1: &I1, input2: &I2| {
+            (f)(input1.clone(), input2.clone())
+        })
+    }
 
-        var1.set(10);
-        dag.stablize();
-        assert_eq!(rejoin.observe(), 24);
+    /// Like [`Incrementars::map2`], but `f` borrows its inputs instead of taking clones of them.
+    pub fn map2_ref<I1: 'static, I2: 'static, O: PartialEq + SendSync + 'static>(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        f: impl Fn(&I1, &I2) -> O + SendSync + 'static,
+    ) -> Map2<I1, I2, O> {
+        self.map2_ref_with_cutoff(input1, input2, Cutoff::default(), f)
     }
 
-    #[test]
-    fn test_bind() {
-        let mut dag = Incrementars::new();
-        let left = dag.var(1);
-        let right = dag.var(2);
-        let left_id = traits::Observable::id(&left);
-        let right_id = traits::Observable::id(&right);
+    /// Like [`Incrementars::map2_ref`], starting out with `cutoff`.
+    pub fn map2_ref_with_cutoff<I1: 'static, I2: 'static, O: SendSync + 'static>(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(&I1, &I2) -> O + SendSync + 'static,
+    ) -> Map2<I1, I2, O> {
+        let id = self.next_id();
+        let value = match (self.read_input(input1.id()), self.read_input(input2.id())) {
+            (Some(input1), Some(input2)) => Some((f)(input1, input2)),
+            _ => None,
+        };
+        self.add_node(
+            id,
+            Box::new(_Map2 {
+                value,
+                input1: input1.as_input(),
+                input2: input2.as_input(),
+                f: Box::new(f),
+                cutoff,
+            }),
+        );
+        Map2::new(id)
+    }
 
-        #[derive(Debug, Clone, Copy)]
-        enum Side {
-            Left,
-            Right,
+    /// Like [`Incrementars::map`], but `f` can fail. Reading a failed node returns the error, and
+    /// nodes reading from it fail along with it without running. They all recover as soon as `f`
+    /// succeeds again.
+    pub fn try_map<I: Clone + 'static, O: PartialEq + SendSync + 'static, E: Display>(
+        &mut self,
+        input: impl Observable<I>,
+        f: impl Fn(I) -> Result<O, E> + SendSync + 'static,
+    ) -> TryMap1<I, O> {
+        self.try_map_with_cutoff(input, Cutoff::default(), f)
+    }
+
+    /// Like [`Incrementars::try_map`], starting out with `cutoff`; see
+    /// [`Incrementars::map_with_cutoff`].
+    pub fn try_map_with_cutoff<I: Clone + 'static, O: SendSync + 'static, E: Display>(
+        &mut self,
+        input: impl Observable<I>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(I) -> Result<O, E> + SendSync + 'static,
+    ) -> TryMap1<I, O> {
+        let id = self.next_id();
+        let f = move |input: &I| (f)(input.clone()).map_err(|err| err.to_string());
+        let (value, message) = match self.read_input(input.id()).map(&f).transpose() {
+            Ok(value) => (value, None),
+            Err(message) => (None, Some(message)),
+        };
+        self.add_node(
+            id,
+            Box::new(_TryMap1 {
+                id,
+                value,
+                input: input.as_input(),
+                f: Box::new(f),
+                cutoff,
+            }),
+        );
+        if let Some(message) = message {
+            self.nodes[id.index].error = Some(IncrError::Failed { node: id, message });
         }
+        TryMap1::new(id)
+    }
 
-        let picker = dag.var(Side::Left);
+    /// Like [`Incrementars::map2`], but `f` can fail; see [`Incrementars::try_map`].
+    pub fn try_map2<
+        I1: Clone + 'static,
+        I2: Clone + 'static,
+        O: PartialEq + SendSync + 'static,
+        E: Display,
+    >(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        f: impl Fn(I1, I2) -> Result<O, E> + SendSync + 'static,
+    ) -> TryMap2<I1, I2, O> {
+        self.try_map2_with_cutoff(input1, input2, Cutoff::default(), f)
+    }
 
-        fn pick(
-            left: Box<Var<i32>>,
-            right: Box<Var<i32>>,
-        ) -> impl Fn(Side) -> Box<dyn Observable<i32>> {
-            move |side| match side {
-                Side::Left => left.clone(),
-                Side::Right => right.clone(),
-            }
+    /// Like [`Incrementars::try_map2`], starting out with `cutoff`; see
+    /// [`Incrementars::map_with_cutoff`].
+    pub fn try_map2_with_cutoff<
+        I1: Clone + 'static,
+        I2: Clone + 'static,
+        O: SendSync + 'static,
+        E: Display,
+    >(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(I1, I2) -> Result<O, E> + SendSync + 'static,
+    ) -> TryMap2<I1, I2, O> {
+        let id = self.next_id();
+        let f = move |input1: &I1, input2: &I2| {
+            (f)(input1.clone(), input2.clone()).map_err(|err| err.to_string())
+        };
+        let value = match (self.read_input(input1.id()), self.read_input(input2.id())) {
+            (Some(input1), Some(input2)) => Some((f)(input1, input2)),
+            _ => None,
+        };
+        let (value, message) = match value.transpose() {
+            Ok(value) => (value, None),
+            Err(message) => (None, Some(message)),
+        };
+        self.add_node(
+            id,
+            Box::new(_TryMap2 {
+                id,
+                value,
+                input1: input1.as_input(),
+                input2: input2.as_input(),
+                f: Box::new(f),
+                cutoff,
+            }),
+        );
+        if let Some(message) = message {
+            self.nodes[id.index].error = Some(IncrError::Failed { node: id, message });
         }
+        TryMap2::new(id)
+    }
 
-        let binder = dag.bind(
-            picker.as_input(),
-            Box::new(pick(left.as_input(), right.as_input())),
+    /// Creates a node whose value is that of the node `f` picks based on `input`. `f` can also
+    /// build new nodes through the [`Scope`] it is given; those are removed from the graph the
+    /// next time `f` runs.
+    pub fn bind<I: Clone + 'static, O: 'static>(
+        &mut self,
+        input: impl Observable<I>,
+        f: impl Fn(I, &mut Scope) -> Input<O> + SendSync + 'static,
+    ) -> Bind1<I, O> {
+        let id = self.next_id();
+        let input = input.as_input();
+        let value = self.read_input::<I>(input.id()).cloned();
+        let value = value.map(|value| Scope::new(self).activate(id, |scope| (f)(value, scope)));
+        let input_changed_at = self.nodes[input.id().index].changed_at;
+        self.add_node(
+            id,
+            Box::new(_Bind1 {
+                id,
+                value,
+                input,
+                f: Box::new(f),
+                input_changed_at,
+            }),
         );
-        let binder_id = binder.id();
+        Bind1::new(id)
+    }
 
-        assert_eq!(
-            dag.dependencies.get(&left_id),
-            Some(vec![binder.id()]).as_ref()
-        );
-        assert_eq!(dag.dependencies.get(&right_id), None);
-        assert_eq!(binder.observe(), 1);
-        picker.set(Side::Right);
-        dag.stablize();
-        assert_eq!(binder.observe(), 2);
-        assert_eq!(
-            dag.dependencies.get(&right_id),
-            Some(vec![binder_id]).as_ref()
+    /// Starts observing a node. Handlers registered on the returned [`Observer`] are called at the
+    /// end of each stabilization, starting with an [`Update::Initialized`] on the next one.
+    pub fn observe<T: Clone + SendSync + 'static>(
+        &mut self,
+        input: impl Observable<T>,
+    ) -> Observer<T> {
+        let id = self.next_id();
+        self.add_node(
+            id,
+            Box::new(_Observer {
+                input: input.as_input(),
+                last: None,
+                handlers: vec![],
+            }),
         );
-        assert_eq!(dag.dependencies.get(&left_id), Some(vec![]).as_ref());
+        self.pending_observers.push(id);
+        self.add_necessary(id);
+        Observer::new(id)
     }
 
-    #[test]
-    fn test_bind_adjust_height_propagaion() {
-        let mut dag = Incrementars::new();
-        let left_root = dag.var(1);
-        let right_root = dag.var(-1);
-        let left_map = dag.map(left_root.as_input(), |x| x * 2);
+    fn is_observer(&mut self, id: NodeId) -> bool {
+        self.nodes[id.index]
+            .node
+            .as_mut()
+            .is_some_and(|node| node.as_observer().is_some())
+    }
 
-        #[derive(Debug, Clone, Copy)]
-        enum Side {
-            Left,
-            Right,
+    /// Removes a node from the graph and frees its slot for reuse. Observers watching the node
+    /// are removed with it and receive [`Update::Invalidated`] at the end of the next
+    /// stabilization; removing an observer itself simply stops it.
+    ///
+    /// Panics if `id` is stale, or if nodes other than observers still depend on it.
+    pub fn remove(&mut self, id: NodeId) {
+        self.assert_contains(id);
+        let dependents = self.nodes[id.index].dependents.clone();
+        if let Some(dependent) = dependents
+            .iter()
+            .find(|dependent| !self.is_observer(**dependent))
+        {
+            panic!(
+                "cannot remove node {:?}: node {:?} still depends on it",
+                id, dependent
+            );
         }
+        dependents.into_iter().for_each(|observer_id| {
+            let observer = self.remove_node(observer_id);
+            self.invalidated.push(observer);
+        });
+        self.remove_node(id);
+        self.invalidate_retired();
+    }
 
-        let picker = dag.var(Side::Right);
+    /// Detaches a node that nothing depends on anymore, handing back the node itself.
+    fn remove_node(&mut self, id: NodeId) -> Box<dyn Node> {
+        if self.nodes[id.index].necessary > 0 {
+            // only observers can be necessary without dependents; drop their own count.
+            self.remove_necessary(id);
+        }
+        let slot = &mut self.nodes[id.index];
+        let node = slot.node.take().unwrap();
+        slot.live = false;
+        slot.dependents.clear();
+        std::mem::take(&mut slot.inputs)
+            .into_iter()
+            .for_each(|input| self.nodes[input.index].dependents.retain(|x| *x != id));
+        if let Some(owned) = self.scopes.remove(&id) {
+            self.retired.extend(owned);
+        }
 
-        fn pick(
-            left: Box<Map1<i32, i32>>,
-            right: Box<Var<i32>>,
-        ) -> impl Fn(Side) -> Box<dyn Observable<i32>> {
-            move |side| match side {
-                Side::Left => left.clone(),
-                Side::Right => right.clone(),
-            }
+        // the next node in this slot starts from scratch, as if the slot were new.
+        let slot = &mut self.nodes[id.index];
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.necessary = 0;
+        slot.stale = false;
+        slot.changed_at = 0;
+        slot.queued_at = 0;
+        slot.ran_at_step = 0;
+        slot.changed_at_step = 0;
+        slot.height = 0;
+        slot.error = None;
+        if self.stabilizing {
+            self.released.push(id.index);
+        } else {
+            self.free.push(id.index);
         }
+        node
+    }
 
-        let binder = dag.bind(
-            picker.as_input(),
-            Box::new(pick(left_map.as_input(), right_root.as_input())),
-        );
+    /// Removes every retired node along with everything downstream of it. Observers found along
+    /// the way receive [`Update::Invalidated`].
+    fn invalidate_retired(&mut self) {
+        while !self.retired.is_empty() {
+            let mut doomed = vec![];
+            let mut seen = Bitmap::new(self.nodes.len());
+            let mut stack = std::mem::take(&mut self.retired);
+            while let Some(id) = stack.pop() {
+                if !self.contains(id) || seen.contains(&id.index) {
+                    continue;
+                }
+                seen.insert(id.index);
+                doomed.push(id);
+                stack.extend(&self.nodes[id.index].dependents);
+            }
+            // dependents sit above their inputs, so going from the highest node down never removes a
+            // node that something still depends on.
+            doomed.sort_by_key(|id| Reverse(self.nodes[id.index].height));
+            doomed.into_iter().for_each(|id| {
+                let is_observer = self.is_observer(id);
+                let node = self.remove_node(id);
+                if is_observer {
+                    self.invalidated.push(node);
+                }
+            });
+        }
+    }
 
-        let map_after_bind = dag.map(binder.as_input(), |n| n * 10);
-        let binder_old_depth = binder.depth();
-        let mabind_old_depth = map_after_bind.depth();
+    /// Brings every necessary node up to date with the vars set since the last stabilization.
+    /// Panics where [`Incrementars::try_stablize`] would return an error.
+    pub fn stablize(&mut self) {
+        if let Err(err) = self.try_stablize() {
+            panic!("{}", err);
+        }
+    }
 
-        dag.print();
-        picker.set(Side::Left);
-        dag.stablize();
-        dag.print();
-        let binder_new_depth = binder.depth();
-        let mabind_new_depth = map_after_bind.depth();
+    /// Like [`Incrementars::stablize`], but reports a bind that picked a removed node or one of
+    /// its own dependents as an error. That bind fails with the error, taking its dependents
+    /// along, until one of its inputs changes and it picks again. The rest of the graph still
+    /// stabilizes. Returns the first error.
+    pub fn try_stablize(&mut self) -> Result<(), IncrError> {
+        self.begin_stabilization();
+        let mut result = Ok(());
+        while let Some((height, id)) = self.queue.pop() {
+            if self.is_ready(height, id) {
+                result = result.and(self.recompute_node(id));
+            }
+        }
+        self.finish_stabilization();
+        result
+    }
 
-        assert_eq!(binder_new_depth, binder_old_depth - 1);
-        assert_eq!(mabind_new_depth, mabind_old_depth - 1);
+    /// Like [`Incrementars::stablize`], but recomputes the nodes queued at each height across up
+    /// to `threads` threads, the calling one included. Each thread gets at least 512 nodes, so
+    /// narrow levels stay on the calling thread. Binds still recompute one at a time on the
+    /// calling thread. Values and observer updates are the same as with `stablize`.
+    #[cfg(feature = "sync")]
+    pub fn stablize_parallel(&mut self, threads: usize) {
+        if let Err(err) = self.try_stablize_parallel(threads) {
+            panic!("{}", err);
+        }
     }
 
-    #[test]
-    fn test_real_life() {
-        let mut dag = Incrementars::new();
-        let length = dag.var(2.0);
-        let area = dag.map(length.as_input(), |x| x * x);
+    /// [`Incrementars::stablize_parallel`], reporting errors like [`Incrementars::try_stablize`].
+    #[cfg(feature = "sync")]
+    pub fn try_stablize_parallel(&mut self, threads: usize) -> Result<(), IncrError> {
+        self.begin_stabilization();
+        let mut result = Ok(());
+        let mut running = Running::new(self);
+        while let Some(height) = running.dag.queue.pop_level(&mut running.pending) {
+            let Running {
+                dag,
+                nodes,
+                pending,
+            } = &mut running;
+            // nodes at the same height never read each other, so they can recompute together.
+            // binds stay behind in `pending` and run afterwards.
+            pending.retain(|&id| {
+                if !dag.is_ready(height, id) {
+                    return false;
+                }
+                if let Some(err) = dag.failed_input(id) {
+                    dag.nodes[id.index].stale = false;
+                    let res = Some(StablizationCallback::Failed(err));
+                    result = std::mem::replace(&mut result, Ok(())).and(dag.apply(id, res));
+                    return false;
+                }
+                if dag.node(id).changes_graph() {
+                    return true;
+                }
+                dag.nodes[id.index].stale = false;
+                nodes.push((id, dag.nodes[id.index].node.take(), None));
+                false
+            });
 
-        // on initial stabalization, area is calculated to be 4.
-        assert_eq!(area.observe(), 4.0);
-        length.set(3.0);
+            let shared = &**dag;
+            let threads = threads.min(nodes.len() / MIN_NODES_PER_THREAD);
+            if threads <= 1 {
+                Self::recompute_chunk(shared, nodes);
+            } else {
+                let chunk_size = nodes.len().div_ceil(threads);
+                std::thread::scope(|scope| {
+                    let mut chunks = nodes.chunks_mut(chunk_size);
+                    let first = chunks.next();
+                    chunks.for_each(|chunk| {
+                        scope.spawn(move || Self::recompute_chunk(shared, chunk));
+                    });
+                    if let Some(chunk) = first {
+                        Self::recompute_chunk(shared, chunk);
+                    }
+                });
+            }
 
-        // right after setting, dag isn't stablized yet.
-        assert_eq!(area.observe(), 4.0);
+            running.put_back();
+            let Running {
+                dag,
+                nodes,
+                pending,
+            } = &mut running;
+            nodes.drain(..).for_each(|(id, _, res)| {
+                result = std::mem::replace(&mut result, Ok(())).and(dag.apply(id, res));
+            });
+            while let Some(id) = pending.pop() {
+                result = std::mem::replace(&mut result, Ok(())).and(dag.recompute_node(id));
+            }
+        }
+        drop(running);
+        self.finish_stabilization();
+        result
+    }
 
-        dag.stablize();
-        assert_eq!(area.observe(), 9.0);
+    #[cfg(feature = "sync")]
+    fn recompute_chunk(dag: &Incrementars, chunk: &mut [TakenNode]) {
+        chunk.iter_mut().for_each(|(id, node, res)| {
+            let node = node.as_deref_mut().unwrap();
+            *res = Self::stablize_node(*id, node, &mut Scope::shared(dag), dag.catch_panics);
+        });
+    }
 
-        let height = dag.var(5.0);
-        let volume = dag.map2(area.as_input(), height.as_input(), |x, y| x * y);
+    /// Recomputes `node`, turning a panic into a failure of the node if `catch_panics` is set.
+    fn stablize_node(
+        id: NodeId,
+        node: &mut dyn Node,
+        scope: &mut Scope,
+        catch_panics: bool,
+    ) -> Option<StablizationCallback> {
+        if !catch_panics {
+            return node.stablize(scope);
+        }
+        catch_unwind(AssertUnwindSafe(|| node.stablize(scope))).unwrap_or_else(|payload| {
+            let message = match payload.downcast::<String>() {
+                Ok(message) => *message,
+                Err(payload) => match payload.downcast_ref::<&str>() {
+                    Some(message) => message.to_string(),
+                    None => "Box<dyn Any>".to_string(),
+                },
+            };
+            Some(StablizationCallback::Failed(IncrError::Panicked {
+                node: id,
+                message,
+            }))
+        })
+    }
 
-        assert_eq!(volume.observe(), 45.0);
+    /// Queues the vars set since the last stabilization, along with stale nodes that became
+    /// necessary.
+    fn begin_stabilization(&mut self) {
+        self.stabilization += 1;
+        self.stabilizing = true;
+        // lists are drained in place, so their buffers are reused by the next stabilization.
+        let mut dirty = std::mem::take(&mut self.dirty);
+        dirty.drain(..).for_each(|id| {
+            if self.contains(id) {
+                self.enqueue(id);
+            }
+        });
+        self.dirty = dirty;
 
-        height.set(10.0);
-        dag.stablize();
-        assert_eq!(volume.observe(), 90.0);
+        let mut recompute = std::mem::take(&mut self.recompute);
+        recompute.drain(..).for_each(|id| {
+            if self.contains(id) && self.nodes[id.index].necessary > 0 {
+                self.enqueue(id);
+            }
+        });
+        self.recompute = recompute;
     }
 
-    #[test]
-    fn test_combinatorical_only_fire_once_at_combine() {
-        use std::sync::atomic::{AtomicUsize, Ordering};
-
-        // Define a global static AtomicUsize counter
-        lazy_static! {
-            static ref GLOBAL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    /// Readies the graph for the next stabilization after a panic cut the current one short. The
+    /// nodes still queued are left to recompute on the next one.
+    fn abort_stabilization(&mut self) {
+        while let Some((_, id)) = self.queue.pop() {
+            self.retry(id);
         }
+        self.stabilizing = false;
+        self.free.append(&mut self.released);
+        self.invalidate_retired();
+    }
 
-        fn increment_counter() {
-            GLOBAL_COUNTER.fetch_add(1, Ordering::SeqCst);
+    /// Makes `id` recompute on the next stabilization, or once it is necessary again.
+    fn retry(&mut self, id: NodeId) {
+        if !self.contains(id) {
+            return;
         }
-
-        fn get_counter() -> usize {
-            GLOBAL_COUNTER.load(Ordering::SeqCst)
+        if self.nodes[id.index].necessary > 0 {
+            self.recompute.push(id);
+        } else {
+            self.nodes[id.index].stale = true;
+        }
+    }
+
+    /// Queues `id` at its height, unless it was already queued in this stabilization. Heights
+    /// guarantee a node runs only after all of its inputs have, so once is enough.
+    fn enqueue(&mut self, id: NodeId) {
+        let slot = &mut self.nodes[id.index];
+        if slot.queued_at != self.stabilization {
+            slot.queued_at = self.stabilization;
+            self.queue.push(slot.height, id);
+        }
+    }
+
+    /// Whether a node popped from `height` should recompute now.
+    fn is_ready(&mut self, height: usize, id: NodeId) -> bool {
+        if !self.contains(id) {
+            // invalidated by a bind earlier in this stabilization.
+            return false;
+        }
+        if self.nodes[id.index].height > height {
+            // raised by a bind rewire after it was queued; wait until its new inputs are done.
+            self.queue.push(self.nodes[id.index].height, id);
+            return false;
+        }
+        true
+    }
+
+    fn recompute_node(&mut self, id: NodeId) -> Result<(), IncrError> {
+        self.nodes[id.index].stale = false;
+        if let Some(err) = self.failed_input(id) {
+            // there is nothing to compute from; fail along with the input instead.
+            return self.apply(id, Some(StablizationCallback::Failed(err)));
+        }
+        // the node comes out of its slot while it runs, so it can read the rest of the graph.
+        let mut running = Running::new(self);
+        running.take(id);
+        let Running { dag, nodes, .. } = &mut running;
+        let node = nodes[0].1.as_deref_mut().unwrap();
+        let catch_panics = dag.catch_panics;
+        let res = Self::stablize_node(id, node, &mut Scope::new(dag), catch_panics);
+        drop(running);
+        self.apply(id, res)
+    }
+
+    /// Acts on what a recomputed node reported: queues dependents of changed nodes, moves edges,
+    /// necessity and heights after a bind picked new nodes, and records failures.
+    fn apply(
+        &mut self,
+        head_id: NodeId,
+        res: Option<StablizationCallback>,
+    ) -> Result<(), IncrError> {
+        self.steps += 1;
+        self.nodes[head_id.index].ran_at_step = self.steps;
+        let recovered = !matches!(res, Some(StablizationCallback::Failed(_)))
+            && self.nodes[head_id.index].error.take().is_some();
+        let result = match res {
+            // dependents still hold the failure, so a node that recovers always propagates.
+            None if recovered => {
+                self.value_changed(head_id);
+                Ok(())
+            }
+            None => Ok(()),
+            Some(StablizationCallback::ValueChanged) => {
+                self.value_changed(head_id);
+                Ok(())
+            }
+            Some(StablizationCallback::NotifyObservers) => {
+                self.pending_observers.push(head_id);
+                Ok(())
+            }
+            Some(StablizationCallback::DependenciesUpdated { from, to }) => {
+                self.value_changed(head_id);
+                let result = self.check_pick(head_id, &to);
+                // a refused pick leaves the bind reading only its inputs, failed until they
+                // change and it picks again.
+                let to = match result {
+                    Ok(()) => to,
+                    Err(_) => {
+                        self.nodes[head_id.index].node.as_mut().unwrap().drop_pick();
+                        vec![]
+                    }
+                };
+                self.rewire(head_id, from, to);
+                // the newly picked node may have failed already.
+                let error = result.clone().err().or_else(|| self.failed_input(head_id));
+                self.nodes[head_id.index].error = error;
+                result
+            }
+            Some(StablizationCallback::Failed(err)) => {
+                if self.nodes[head_id.index].error.as_ref() != Some(&err) {
+                    self.nodes[head_id.index].error = Some(err);
+                    if self.is_observer(head_id) {
+                        self.pending_observers.push(head_id);
+                    } else {
+                        self.value_changed(head_id);
+                    }
+                }
+                Ok(())
+            }
+        };
+        self.invalidate_retired();
+        result
+    }
+
+    fn value_changed(&mut self, id: NodeId) {
+        self.nodes[id.index].changed_at = self.stabilization;
+        self.nodes[id.index].changed_at_step = self.steps;
+        let dependents = std::mem::take(&mut self.nodes[id.index].dependents);
+        dependents.iter().for_each(|dependent| {
+            let slot = &mut self.nodes[dependent.index];
+            if slot.necessary == 0 {
+                // nobody is looking; recompute once it becomes necessary again.
+                slot.stale = true;
+            } else {
+                self.enqueue(*dependent);
+            }
+        });
+        self.nodes[id.index].dependents = dependents;
+    }
+
+    /// Fails if one of `to` is gone or depends on `head_id`, so `head_id` cannot read from it.
+    fn check_pick(&self, head_id: NodeId, to: &[NodeId]) -> Result<(), IncrError> {
+        to.iter().try_for_each(|id| self.check_contains(*id))?;
+        match to.iter().find_map(|id| self.find_cycle(head_id, *id)) {
+            Some(cycle) => Err(IncrError::Cycle(cycle)),
+            None => Ok(()),
+        }
+    }
+
+    /// Moves `head_id` from reading `from` to reading `to`.
+    fn rewire(&mut self, head_id: NodeId, from: Vec<NodeId>, to: Vec<NodeId>) {
+        // a bind can pick one of its own inputs, so drop only the edge for this pick.
+        from.iter().for_each(|id| {
+            let dependents = &mut self.nodes[id.index].dependents;
+            if let Some(position) = dependents.iter().position(|x| *x == head_id) {
+                dependents.swap_remove(position);
+            }
+        });
+        to.iter().for_each(|id| {
+            self.nodes[id.index].dependents.push(head_id);
+        });
+        self.nodes[head_id.index].inputs = self.node(head_id).inputs();
+        // the newly picked nodes take over necessity from the old ones. Add first so
+        // that nodes shared by both sides stay necessary throughout.
+        if self.nodes[head_id.index].necessary > 0 {
+            to.iter().for_each(|id| self.add_necessary(*id));
+            from.iter().for_each(|id| self.remove_necessary(*id));
+        }
+
+        // only the bind and whatever sits above it can need a new height.
+        let mut adjust_queue = vec![head_id];
+
+        while let Some(node_id) = adjust_queue.pop() {
+            let max_upstream_height = self.nodes[node_id.index]
+                .inputs
+                .iter()
+                .map(|input| self.nodes[input.index].height)
+                .max();
+
+            if let Some(raw_height) = max_upstream_height {
+                let old_height = self.nodes[node_id.index].height;
+                let new_height = raw_height + 1;
+                if new_height > old_height {
+                    self.nodes[node_id.index].height = new_height;
+                    adjust_queue.extend(&self.nodes[node_id.index].dependents);
+                }
+            }
+        }
+
+        // nodes that just became necessary may have missed changes while nobody
+        // looked. Bring them up to date, then let the bind forward them once more.
+        if !self.recompute.is_empty() {
+            let mut stale = std::mem::take(&mut self.recompute);
+            stale.drain(..).for_each(|id| self.enqueue(id));
+            self.recompute = stale;
+            self.queue.push(self.nodes[head_id.index].height, head_id);
+        }
+    }
+
+    /// The path from `node` up through its dependents to `input`, if there is one. Making `node`
+    /// read from `input` would close that path into a cycle.
+    fn find_cycle(&self, node: NodeId, input: NodeId) -> Option<Vec<NodeId>> {
+        let input_height = self.nodes[input.index].height;
+        // dependents sit above their inputs, so nothing below `node` can depend on it.
+        if input_height < self.nodes[node.index].height {
+            return None;
+        }
+        let mut parents = HashMap::new();
+        let mut stack = vec![node];
+        while let Some(id) = stack.pop() {
+            if id == input {
+                let mut path = vec![id];
+                while let Some(parent) = parents.get(&path[path.len() - 1]) {
+                    path.push(*parent);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            self.nodes[id.index]
+                .dependents
+                .iter()
+                .for_each(|dependent| {
+                    let reachable = self.nodes[dependent.index].height <= input_height;
+                    if reachable && *dependent != node && !parents.contains_key(dependent) {
+                        parents.insert(*dependent, id);
+                        stack.push(*dependent);
+                    }
+                });
+        }
+        None
+    }
+
+    /// Runs the handlers of observers that saw a change, and tells observers of removed nodes.
+    fn finish_stabilization(&mut self) {
+        // the queue is empty, so nothing links the slots freed during the pass anymore.
+        self.stabilizing = false;
+        self.free.append(&mut self.released);
+        let mut running = Running::new(self);
+        let Running { dag, pending, .. } = &mut running;
+        // observers still pending if a handler panics are notified on the next stabilization.
+        std::mem::swap(pending, &mut dag.pending_observers);
+        pending.sort_unstable_by_key(|&id| Reverse(id));
+        pending.dedup();
+        while let Some(id) = running.pending.pop() {
+            if !running.dag.contains(id) {
+                continue;
+            }
+            running.take(id);
+            let Running { dag, nodes, .. } = &mut running;
+            if let Some(observer) = nodes[0].1.as_mut().unwrap().as_observer() {
+                observer.notify(dag);
+            }
+            running.put_back();
+            running.nodes.clear();
+        }
+        let Running { dag, pending, .. } = &mut running;
+        std::mem::swap(pending, &mut dag.pending_observers);
+        drop(running);
+        let mut invalidated = std::mem::take(&mut self.invalidated);
+        invalidated.drain(..).for_each(|mut node| {
+            if let Some(observer) = node.as_observer() {
+                observer.invalidate(self);
+            }
+        });
+        self.invalidated = invalidated;
+        #[cfg(debug_assertions)]
+        self.check_invariants();
+    }
+
+    /// Checks the bookkeeping behind the graph, panicking with a description of the first problem
+    /// found. Debug builds run it at the end of every stabilization.
+    ///
+    /// Every node must sit above its inputs, and inputs and dependents must mirror each other and
+    /// what each bind currently reads. Necessity must match the necessary dependents. Set vars
+    /// and stale necessary nodes must be waiting for the next stabilization, so right after one
+    /// none are left. Necessary nodes reading from a failed node must have failed as well, and
+    /// the others must have recomputed since any of their inputs last changed. Unnecessary nodes
+    /// that missed a change must fail to be read.
+    pub fn check_invariants(&self) {
+        self.nodes.iter().enumerate().for_each(|(index, slot)| {
+            if !slot.live {
+                return;
+            }
+            let id = NodeId {
+                index,
+                generation: slot.generation,
+            };
+            let node = self.node(id);
+            // other nodes never change what they read from after being built.
+            assert!(
+                !node.changes_graph() || node.reads_from(&slot.inputs),
+                "bind {:?} reads from other nodes than recorded",
+                id
+            );
+            slot.inputs.iter().for_each(|input| {
+                assert!(
+                    self.contains(*input),
+                    "node {:?} reads from removed node {:?}",
+                    id,
+                    input
+                );
+                let input_slot = &self.nodes[input.index];
+                assert!(
+                    input_slot.height < slot.height,
+                    "node {:?} at height {} is not above its input {:?} at height {}",
+                    id,
+                    slot.height,
+                    input,
+                    input_slot.height
+                );
+                assert!(
+                    input_slot.dependents.contains(&id),
+                    "node {:?} is missing from the dependents of its input {:?}",
+                    id,
+                    input
+                );
+            });
+            slot.dependents.iter().for_each(|dependent| {
+                assert!(
+                    self.contains(*dependent) && self.nodes[dependent.index].inputs.contains(&id),
+                    "node {:?} lists {:?} as a dependent, which does not read from it",
+                    id,
+                    dependent
+                );
+            });
+
+            let necessary_dependents = slot
+                .dependents
+                .iter()
+                .filter(|dependent| self.nodes[dependent.index].necessary > 0)
+                .count() as u32;
+            // observers have no dependents, and count themselves once.
+            assert!(
+                slot.necessary == necessary_dependents
+                    || (slot.dependents.is_empty() && slot.necessary == 1),
+                "node {:?} is necessary {} times, but has {} necessary dependents",
+                id,
+                slot.necessary,
+                necessary_dependents
+            );
+            assert!(
+                !node.is_dirty() || self.dirty.contains(&id),
+                "var {:?} is dirty but not queued for stabilization",
+                id
+            );
+            if slot.necessary == 0 {
+                // nothing keeps these up to date, so reading one must fail once it fell behind.
+                let behind = slot
+                    .inputs
+                    .iter()
+                    .any(|input| self.nodes[input.index].changed_at_step >= slot.ran_at_step);
+                assert!(
+                    !behind || self.check_current(id).is_err(),
+                    "node {:?} is not observed and missed a change, but can still be read",
+                    id
+                );
+                return;
+            }
+            assert!(
+                !slot.stale || self.recompute.contains(&id),
+                "node {:?} is necessary and stale but not queued to recompute",
+                id
+            );
+            let input_failed = slot
+                .inputs
+                .iter()
+                .any(|input| self.nodes[input.index].error.is_some());
+            assert!(
+                slot.stale || !input_failed || slot.error.is_some(),
+                "node {:?} reads from a failed node but has not failed",
+                id
+            );
+            // whatever a node holds must come from the current values of its inputs.
+            slot.inputs.iter().for_each(|input| {
+                assert!(
+                    slot.stale
+                        || self.nodes[input.index].changed_at_step < slot.ran_at_step
+                        || self.recompute.contains(&id),
+                    "node {:?} has not recomputed since its input {:?} changed",
+                    id,
+                    input
+                );
+            });
+        });
+    }
+
+    pub fn print(&self) {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.live)
+            .for_each(|(index, slot)| {
+                println!("dep | {:?} depends on {}", slot.dependents, index);
+                println!("node | {} @ {}", index, slot.height);
+            })
+    }
+}
+
+// a node out of its slot while it runs, along with what running it produced.
+type TakenNode = (NodeId, Option<Box<dyn Node>>, Option<StablizationCallback>);
+
+/// Nodes taken out of their slots to run. Dropping the guard puts back any still out, so a node
+/// function that panics cannot leave its slot empty. If that happens while unwinding, the nodes
+/// it held, along with those popped but not yet run and those still queued, recompute on the
+/// next stabilization instead.
+struct Running<'a> {
+    dag: &'a mut Incrementars,
+    nodes: Vec<TakenNode>,
+    // popped from the queue, but not yet run.
+    pending: Vec<NodeId>,
+}
+
+impl<'a> Running<'a> {
+    fn new(dag: &'a mut Incrementars) -> Self {
+        let nodes = std::mem::take(&mut dag.running);
+        Self {
+            dag,
+            nodes,
+            pending: vec![],
+        }
+    }
+
+    fn take(&mut self, id: NodeId) {
+        let node = self.dag.nodes[id.index].node.take();
+        self.nodes.push((id, node, None));
+    }
+
+    fn put_back(&mut self) {
+        self.nodes.iter_mut().for_each(|(id, node, _)| {
+            if let Some(node) = node.take() {
+                self.dag.nodes[id.index].node = Some(node);
+            }
+        });
+    }
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.put_back();
+        if std::thread::panicking() {
+            self.nodes.iter().for_each(|(id, ..)| self.dag.retry(*id));
+            self.pending.drain(..).for_each(|id| self.dag.retry(id));
+            self.dag.abort_stabilization();
+        }
+        self.nodes.clear();
+        self.dag.running = std::mem::take(&mut self.nodes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use lazy_static::lazy_static;
+
+    use super::*;
+
+    /// Collects every update `observer` receives from now on.
+    fn record<T: Clone + SendSync + 'static>(
+        dag: &mut Incrementars,
+        observer: Observer<T>,
+    ) -> Arc<Mutex<Vec<Update<T>>>> {
+        let updates = Arc::new(Mutex::new(vec![]));
+        let sink = updates.clone();
+        observer.on_update(dag, move |update| sink.lock().unwrap().push(update));
+        updates
+    }
+
+    #[test]
+    fn var_instantiation() {
+        let mut dag = Incrementars::new();
+        let var = dag.var(0);
+        var.set(&mut dag, 10);
+        assert_eq!(var.observe(&dag), 10);
+    }
+
+    #[test]
+    fn map_instantiation() {
+        let mut dag = Incrementars::new();
+        let var = dag.var(0);
+        let map = dag.map(var, |x| x + 1);
+        assert_eq!(map.observe(&dag), 1);
+    }
+
+    #[test]
+    fn bifurcate() {
+        let mut dag = Incrementars::new();
+        let var = dag.var(0);
+        let map = dag.map(var.as_input(), |x| x + 1);
+        let map2 = dag.map(var.as_input(), |x| x + 1);
+        dag.observe(map.as_input());
+        dag.observe(map2.as_input());
+        assert_eq!(map.observe(&dag), 1);
+        assert_eq!(map2.observe(&dag), 1);
+
+        var.set(&mut dag, 10);
+        assert_eq!(map.observe(&dag), 1);
+        dag.stablize();
+        assert_eq!(map.observe(&dag), 11);
+        assert_eq!(map2.observe(&dag), 11);
+    }
+
+    #[test]
+    fn test_map2() {
+        let mut dag = Incrementars::new();
+        let var1 = dag.var(50);
+        let var2 = dag.var(" dollars");
+        let map2 = dag.map2(var1.as_input(), var2.as_input(), |x, y| x.to_string() + y);
+        assert_eq!(map2.observe(&dag), "50 dollars");
+    }
+
+    #[test]
+    fn test_combinatoric() {
+        let mut dag = Incrementars::new();
+        let var1 = dag.var(50);
+        let plus_one = |x| x + 1;
+        let var21 = dag.map(var1.as_input(), plus_one);
+        let var22 = dag.map(var21.as_input(), plus_one);
+        let var23 = dag.map(var22.as_input(), plus_one);
+        let var31 = dag.map(var1.as_input(), plus_one);
+        let rejoin = dag.map2(var31.as_input(), var23.as_input(), |x, y| x + y);
+        dag.observe(rejoin.as_input());
+
+        var1.set(&mut dag, 10);
+        dag.stablize();
+        assert_eq!(rejoin.observe(&dag), 24);
+    }
+
+    #[test]
+    fn test_bind() {
+        let mut dag = Incrementars::new();
+        let left = dag.var(1);
+        let right = dag.var(2);
+        let left_id = left.id();
+        let right_id = right.id();
+
+        #[derive(Debug, Clone, Copy)]
+        enum Side {
+            Left,
+            Right,
+        }
+
+        let picker = dag.var(Side::Left);
+
+        fn pick(left: Input<i32>, right: Input<i32>) -> impl Fn(Side, &mut Scope) -> Input<i32> {
+            move |side, _| match side {
+                Side::Left => left,
+                Side::Right => right,
+            }
+        }
+
+        let binder = dag.bind(picker.as_input(), pick(left.as_input(), right.as_input()));
+        let binder_id = binder.id();
+        dag.observe(binder.as_input());
+
+        assert_eq!(dag.nodes[left_id.index].dependents, vec![binder.id()]);
+        assert_eq!(dag.nodes[right_id.index].dependents, vec![]);
+        assert_eq!(binder.observe(&dag), 1);
+        picker.set(&mut dag, Side::Right);
+        dag.stablize();
+        assert_eq!(binder.observe(&dag), 2);
+        assert_eq!(dag.nodes[right_id.index].dependents, vec![binder_id]);
+        assert_eq!(dag.nodes[left_id.index].dependents, vec![]);
+    }
+
+    #[test]
+    fn test_bind_adjust_height_propagaion() {
+        let mut dag = Incrementars::new();
+        let left_root = dag.var(1);
+        let right_root = dag.var(-1);
+        let left_map = dag.map(left_root.as_input(), |x| x * 2);
+
+        #[derive(Debug, Clone, Copy)]
+        enum Side {
+            Left,
+            Right,
+        }
+
+        let picker = dag.var(Side::Right);
+
+        fn pick(left: Input<i32>, right: Input<i32>) -> impl Fn(Side, &mut Scope) -> Input<i32> {
+            move |side, _| match side {
+                Side::Left => left,
+                Side::Right => right,
+            }
+        }
+
+        let binder = dag.bind(
+            picker.as_input(),
+            pick(left_map.as_input(), right_root.as_input()),
+        );
+
+        let map_after_bind = dag.map(binder.as_input(), |n| n * 10);
+        dag.observe(map_after_bind.as_input());
+        let binder_old_height = binder.height(&dag);
+        let mabind_old_height = map_after_bind.height(&dag);
+
+        dag.print();
+        picker.set(&mut dag, Side::Left);
+        dag.stablize();
+        dag.print();
+        let binder_new_height = binder.height(&dag);
+        let mabind_new_height = map_after_bind.height(&dag);
+
+        assert_eq!(binder_new_height, binder_old_height + 1);
+        assert_eq!(mabind_new_height, mabind_old_height + 1);
+    }
+
+    #[test]
+    fn test_real_life() {
+        let mut dag = Incrementars::new();
+        let length = dag.var(2.0);
+        let area = dag.map(length.as_input(), |x| x * x);
+        dag.observe(area.as_input());
+
+        // on initial stabalization, area is calculated to be 4.
+        assert_eq!(area.observe(&dag), 4.0);
+        length.set(&mut dag, 3.0);
+
+        // right after setting, dag isn't stablized yet.
+        assert_eq!(area.observe(&dag), 4.0);
+
+        dag.stablize();
+        assert_eq!(area.observe(&dag), 9.0);
+
+        let height = dag.var(5.0);
+        let volume = dag.map2(area.as_input(), height.as_input(), |x, y| x * y);
+        dag.observe(volume.as_input());
+
+        assert_eq!(volume.observe(&dag), 45.0);
+
+        height.set(&mut dag, 10.0);
+        dag.stablize();
+        assert_eq!(volume.observe(&dag), 90.0);
+    }
+
+    #[test]
+    fn test_combinatorical_only_fire_once_at_combine() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Define a global static AtomicUsize counter
+        lazy_static! {
+            static ref GLOBAL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+        }
+
+        fn increment_counter() {
+            GLOBAL_COUNTER.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn get_counter() -> usize {
+            GLOBAL_COUNTER.load(Ordering::SeqCst)
         }
 
         let mut dag = Incrementars::new();
         let var1 = dag.var(1);
         let plus_one = |x| x + 1;
-        let left1 = dag.map(var1.as_input(), plus_one.clone());
-        let left2 = dag.map(left1.as_input(), plus_one.clone());
-        let left3 = dag.map(left2.as_input(), plus_one.clone());
+        let left1 = dag.map(var1.as_input(), plus_one);
+        let left2 = dag.map(left1.as_input(), plus_one);
+        let left3 = dag.map(left2.as_input(), plus_one);
 
-        let right = dag.map(var1.as_input(), plus_one.clone());
+        let right = dag.map(var1.as_input(), plus_one);
 
         fn incr_counter(_: i32, _: i32) {
             increment_counter();
@@ -445,4 +1558,1503 @@ mod tests {
         dag.map2(left3.as_input(), right.as_input(), incr_counter);
         assert_eq!(get_counter(), 1);
     }
+
+    #[test]
+    fn test_cutoff_stops_propagation() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        lazy_static! {
+            static ref DOWNSTREAM_RUNS: AtomicUsize = AtomicUsize::new(0);
+        }
+
+        fn count(x: i32) -> i32 {
+            DOWNSTREAM_RUNS.fetch_add(1, Ordering::SeqCst);
+            x
+        }
+
+        let mut dag = Incrementars::new();
+        let var = dag.var(1);
+        let tens = dag.map(var.as_input(), |x| x / 10);
+        let downstream = dag.map(tens.as_input(), count);
+        dag.observe(downstream.as_input());
+        assert_eq!(DOWNSTREAM_RUNS.load(Ordering::SeqCst), 1);
+
+        // 1 / 10 == 5 / 10, so the downstream node should not fire.
+        var.set(&mut dag, 5);
+        dag.stablize();
+        assert_eq!(DOWNSTREAM_RUNS.load(Ordering::SeqCst), 1);
+
+        var.set(&mut dag, 25);
+        dag.stablize();
+        assert_eq!(DOWNSTREAM_RUNS.load(Ordering::SeqCst), 2);
+        assert_eq!(downstream.observe(&dag), 2);
+    }
+
+    #[test]
+    fn test_cutoff_never_and_custom() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        lazy_static! {
+            static ref NEVER_RUNS: AtomicUsize = AtomicUsize::new(0);
+            static ref CUSTOM_RUNS: AtomicUsize = AtomicUsize::new(0);
+        }
+
+        fn count_never(x: f64) -> f64 {
+            NEVER_RUNS.fetch_add(1, Ordering::SeqCst);
+            x
+        }
+
+        fn count_custom(x: f64, _: f64) -> f64 {
+            CUSTOM_RUNS.fetch_add(1, Ordering::SeqCst);
+            x
+        }
+
+        let mut dag = Incrementars::new();
+        let var = dag.var(1.0);
+        let never = dag.map(var.as_input(), |x| x);
+        never.set_cutoff(&mut dag, Cutoff::never());
+        let never_leaf = dag.map(never.as_input(), count_never);
+        dag.observe(never_leaf.as_input());
+
+        let zero = dag.var(0.0);
+        let tolerant = dag.map2(var.as_input(), zero.as_input(), |x, y| x + y);
+        tolerant.set_cutoff(
+            &mut dag,
+            Cutoff::custom(|old: &f64, new: &f64| (old - new).abs() < 0.5),
+        );
+        let custom_leaf = dag.map2(tolerant.as_input(), zero.as_input(), count_custom);
+        dag.observe(custom_leaf.as_input());
+
+        // same value: `never` still propagates, while `tolerant` does not.
+        var.set(&mut dag, 1.0);
+        dag.stablize();
+        assert_eq!(NEVER_RUNS.load(Ordering::SeqCst), 2);
+        assert_eq!(CUSTOM_RUNS.load(Ordering::SeqCst), 1);
+
+        // within tolerance, the old value is kept.
+        var.set(&mut dag, 1.25);
+        dag.stablize();
+        assert_eq!(CUSTOM_RUNS.load(Ordering::SeqCst), 1);
+        assert_eq!(tolerant.observe(&dag), 1.0);
+
+        var.set(&mut dag, 2.0);
+        dag.stablize();
+        assert_eq!(CUSTOM_RUNS.load(Ordering::SeqCst), 2);
+        assert_eq!(tolerant.observe(&dag), 2.0);
+    }
+
+    #[test]
+    fn test_with_cutoff_builds_outputs_without_partial_eq() {
+        // deliberately not `PartialEq`.
+        #[derive(Clone)]
+        struct Reading(f64);
+
+        let mut dag = Incrementars::new();
+        let var = dag.var(1.0);
+        let reading = dag.map_with_cutoff(var, Cutoff::never(), Reading);
+        let summed = dag.map2_with_cutoff(
+            reading,
+            var,
+            Cutoff::custom(|old: &Reading, new: &Reading| (old.0 - new.0).abs() < 0.5),
+            |reading, x| Reading(reading.0 + x),
+        );
+        let scaled = dag.map3_with_cutoff(reading, summed, var, Cutoff::never(), |r, s, x| {
+            Reading(r.0 * s.0 * x)
+        });
+        let checked = dag.try_map_with_cutoff(scaled, Cutoff::never(), |scaled| match scaled.0 {
+            x if x < 100.0 => Ok(scaled),
+            _ => Err("too large"),
+        });
+        let observer = dag.observe(checked);
+        dag.stablize();
+        assert_eq!(observer.value(&dag).0, 2.0);
+
+        var.set(&mut dag, 1.2);
+        dag.stablize();
+        assert_eq!(summed.observe(&dag).0, 2.0);
+        assert_eq!(observer.value(&dag).0, 1.2 * 2.0 * 1.2);
+
+        var.set(&mut dag, 10.0);
+        dag.stablize();
+        assert!(observer.error(&dag).is_some());
+    }
+
+    #[test]
+    fn test_capturing_closures() {
+        let mut dag = Incrementars::new();
+        let qty = dag.var(3);
+        let px = dag.var(100);
+
+        let multiplier = 25;
+        let lot = dag.map(qty.as_input(), move |q| q * multiplier);
+        let fees: Vec<i32> = vec![1, 2, 3];
+        let notional = dag.map2(lot.as_input(), px.as_input(), move |l, p| {
+            l * p + fees.iter().sum::<i32>()
+        });
+        dag.observe(notional.as_input());
+        assert_eq!(notional.observe(&dag), 7506);
+
+        qty.set(&mut dag, 4);
+        dag.stablize();
+        assert_eq!(notional.observe(&dag), 10006);
+    }
+
+    #[test]
+    fn test_map_n() {
+        let mut dag = Incrementars::new();
+        let a = dag.var(1);
+        let b = dag.var(2);
+        let c = dag.var(3);
+        let d = dag.var(4);
+        let e = dag.var(5);
+        let f = dag.var(6);
+        let deep = dag.map(a.as_input(), |x| x * 100);
+
+        let sum3 = dag.map3(a.as_input(), b.as_input(), c.as_input(), |a, b, c| {
+            a + b + c
+        });
+        let sum6 = dag.map6(
+            deep.as_input(),
+            b.as_input(),
+            c.as_input(),
+            d.as_input(),
+            e.as_input(),
+            f.as_input(),
+            |a, b, c, d, e, f| a + b + c + d + e + f,
+        );
+        dag.observe(sum3.as_input());
+        dag.observe(sum6.as_input());
+        assert_eq!(sum3.observe(&dag), 6);
+        assert_eq!(sum6.observe(&dag), 120);
+
+        // a single node sitting right above its highest input.
+        assert_eq!(sum3.height(&dag), a.height(&dag) + 1);
+        assert_eq!(sum6.height(&dag), deep.height(&dag) + 1);
+
+        a.set(&mut dag, 2);
+        f.set(&mut dag, 10);
+        dag.stablize();
+        assert_eq!(sum3.observe(&dag), 7);
+        assert_eq!(sum6.observe(&dag), 224);
+    }
+
+    #[test]
+    fn test_non_copy_var() {
+        #[derive(Clone, PartialEq, Debug)]
+        struct Book {
+            bids: Vec<(u32, u32)>,
+        }
+
+        let mut dag = Incrementars::new();
+        let name = dag.var(String::from("ES"));
+        let book = dag.var(Book { bids: vec![] });
+        let depth = dag.map(book.as_input(), |b| b.bids.len());
+        let label = dag.map2(name.as_input(), depth.as_input(), |n, d| format!("{n}:{d}"));
+        dag.observe(label.as_input());
+        assert_eq!(label.observe(&dag), "ES:0");
+
+        book.update(&mut dag, |b| b.bids.push((100, 5)));
+        book.update(&mut dag, |b| b.bids.push((99, 3)));
+        assert_eq!(label.observe(&dag), "ES:0");
+        dag.stablize();
+        assert_eq!(label.observe(&dag), "ES:2");
+
+        name.set(&mut dag, String::from("NQ"));
+        dag.stablize();
+        assert_eq!(label.observe(&dag), "NQ:2");
+        assert_eq!(book.observe(&dag).bids, vec![(100, 5), (99, 3)]);
+    }
+
+    #[test]
+    fn test_var_cutoff() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        lazy_static! {
+            static ref TICKS: AtomicUsize = AtomicUsize::new(0);
+        }
+
+        fn count(px: f64) -> f64 {
+            TICKS.fetch_add(1, Ordering::SeqCst);
+            px
+        }
+
+        let mut dag = Incrementars::new();
+        let px = dag.var(100.0);
+        px.set_cutoff(&mut dag, Cutoff::partial_eq());
+        let counted = dag.map(px.as_input(), count);
+        dag.observe(counted.as_input());
+        assert_eq!(TICKS.load(Ordering::SeqCst), 1);
+
+        // identical tick leaves the var clean.
+        px.set(&mut dag, 100.0);
+        assert!(!px.is_dirty(&dag));
+        dag.stablize();
+        assert_eq!(TICKS.load(Ordering::SeqCst), 1);
+
+        // compared against the last stabilized value, not the last set.
+        px.set(&mut dag, 101.0);
+        px.set(&mut dag, 100.0);
+        assert!(!px.is_dirty(&dag));
+        dag.stablize();
+        assert_eq!(TICKS.load(Ordering::SeqCst), 1);
+
+        px.update(&mut dag, |p| *p += 1.0);
+        dag.stablize();
+        assert_eq!(TICKS.load(Ordering::SeqCst), 2);
+
+        // a set within tolerance is dropped, so the var agrees with what was computed from it.
+        let last = dag.var(100.0);
+        last.set_cutoff(
+            &mut dag,
+            Cutoff::custom(|old: &f64, new: &f64| (old - new).abs() < 0.5),
+        );
+        let notional = dag.map(last, |px| px * 10.0);
+        dag.observe(notional);
+        last.set(&mut dag, 100.3);
+        dag.stablize();
+        assert_eq!(last.observe(&dag), 100.0);
+        assert_eq!(notional.observe(&dag), 1000.0);
+        last.set(&mut dag, 100.6);
+        dag.stablize();
+        assert_eq!(last.observe(&dag), 100.6);
+        assert_eq!(notional.observe(&dag), 1006.0);
+
+        // default policy still propagates every set.
+        let qty = dag.var(1);
+        qty.set(&mut dag, 1);
+        assert!(qty.is_dirty(&dag));
+    }
+
+    #[test]
+    fn test_observer_on_update() {
+        let mut dag = Incrementars::new();
+        let var = dag.var(1);
+        let tens = dag.map(var.as_input(), |x| x / 10);
+        let observer = dag.observe(tens.as_input());
+
+        let updates = record(&mut dag, observer);
+
+        var.set(&mut dag, 15);
+        dag.stablize();
+        assert_eq!(*updates.lock().unwrap(), vec![Update::Initialized(1)]);
+
+        // cut off upstream, so no update.
+        var.set(&mut dag, 19);
+        dag.stablize();
+        assert_eq!(updates.lock().unwrap().len(), 1);
+
+        var.set(&mut dag, 42);
+        dag.stablize();
+        assert_eq!(
+            updates.lock().unwrap().last(),
+            Some(&Update::Changed { old: 1, new: 4 })
+        );
+        assert_eq!(observer.value(&dag), 4);
+    }
+
+    #[test]
+    fn test_observer_through_bind() {
+        let mut dag = Incrementars::new();
+        let left = dag.var(1);
+        let use_left = dag.var(true);
+        let binder = dag.bind(use_left.as_input(), move |_, _| left.as_input());
+        let observer = dag.observe(binder.as_input());
+        let updates = record(&mut dag, observer);
+        dag.stablize();
+
+        left.set(&mut dag, 2);
+        dag.stablize();
+        assert_eq!(
+            *updates.lock().unwrap(),
+            vec![Update::Initialized(1), Update::Changed { old: 1, new: 2 }]
+        );
+    }
+
+    #[test]
+    fn test_only_necessary_nodes_compute() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        lazy_static! {
+            static ref LEFT_RUNS: AtomicUsize = AtomicUsize::new(0);
+            static ref RIGHT_RUNS: AtomicUsize = AtomicUsize::new(0);
+        }
+
+        fn count_left(x: i32) -> i32 {
+            LEFT_RUNS.fetch_add(1, Ordering::SeqCst);
+            x + 1
+        }
+
+        fn count_right(x: i32) -> i32 {
+            RIGHT_RUNS.fetch_add(1, Ordering::SeqCst);
+            x + 2
+        }
+
+        let mut dag = Incrementars::new();
+        let var = dag.var(0);
+        let left = dag.map(var.as_input(), count_left);
+        let right = dag.map(var.as_input(), count_right);
+        let left_plus = dag.map(left.as_input(), |x| x * 10);
+        dag.observe(left_plus.as_input());
+
+        // `right` has no path to an observer, so it sits out.
+        var.set(&mut dag, 1);
+        dag.stablize();
+        assert_eq!(LEFT_RUNS.load(Ordering::SeqCst), 2);
+        assert_eq!(RIGHT_RUNS.load(Ordering::SeqCst), 1);
+        assert_eq!(right.try_observe(&dag), Err(IncrError::Stale(right.id())));
+        assert_eq!(left_plus.observe(&dag), 20);
+
+        // observing it again brings it up to date on the next stabilization.
+        let observer = dag.observe(right.as_input());
+        dag.stablize();
+        assert_eq!(RIGHT_RUNS.load(Ordering::SeqCst), 2);
+        assert_eq!(observer.value(&dag), 3);
+
+        var.set(&mut dag, 2);
+        dag.stablize();
+        assert_eq!(RIGHT_RUNS.load(Ordering::SeqCst), 3);
+        assert_eq!(right.observe(&dag), 4);
+    }
+
+    #[test]
+    fn test_unobserved_reads_are_current_or_fail() {
+        let mut dag = Incrementars::new();
+        let x = dag.var(1);
+        let doubled = dag.map(x, |x| x * 2);
+        let plus_one = dag.map(doubled, |x| x + 1);
+        assert_eq!(plus_one.try_observe(&dag), Ok(3));
+
+        // only `doubled` is marked stale, but `plus_one` missed the change through it.
+        x.set(&mut dag, 5);
+        dag.stablize();
+        assert_eq!(x.try_observe(&dag), Ok(5));
+        assert_eq!(
+            doubled.try_observe(&dag),
+            Err(IncrError::Stale(doubled.id()))
+        );
+        assert_eq!(
+            plus_one.try_observe(&dag),
+            Err(IncrError::Stale(plus_one.id()))
+        );
+
+        let observer = dag.observe(plus_one);
+        dag.stablize();
+        assert_eq!(observer.value(&dag), 11);
+        assert_eq!(doubled.try_observe(&dag), Ok(10));
+    }
+
+    #[test]
+    fn test_abandoned_bind_branch_is_unnecessary() {
+        let mut dag = Incrementars::new();
+        let left = dag.var(1);
+        let right = dag.var(2);
+        let left_map = dag.map(left.as_input(), |x| x * 10);
+        let right_map = dag.map(right.as_input(), |x| x * 10);
+        let use_left = dag.var(true);
+
+        let binder = dag.bind(use_left.as_input(), move |use_left, _| -> Input<i32> {
+            if use_left {
+                left_map.as_input()
+            } else {
+                right_map.as_input()
+            }
+        });
+        dag.observe(binder.as_input());
+        assert!(dag.nodes[left_map.id().index].necessary > 0);
+        assert_eq!(dag.nodes[right_map.id().index].necessary, 0);
+
+        use_left.set(&mut dag, false);
+        dag.stablize();
+        assert_eq!(binder.observe(&dag), 20);
+        assert_eq!(dag.nodes[left_map.id().index].necessary, 0);
+        assert!(dag.nodes[right_map.id().index].necessary > 0);
+
+        // the abandoned branch no longer follows its input, and says so when read.
+        left.set(&mut dag, 5);
+        dag.stablize();
+        let stale = IncrError::Stale(left_map.id());
+        assert_eq!(left_map.try_observe(&dag), Err(stale));
+    }
+
+    #[test]
+    fn test_remove_reuses_slot_with_new_generation() {
+        let mut dag = Incrementars::new();
+        let var = dag.var(1);
+        let doubled = dag.map(var.as_input(), |x| x * 2);
+        let observer = dag.observe(doubled.as_input());
+        let updates = record(&mut dag, observer);
+        dag.stablize();
+
+        let old_id = doubled.id();
+        dag.remove(old_id);
+        assert!(!dag.contains(old_id));
+        assert!(!dag.contains(observer.id()));
+        assert_eq!(dag.nodes[var.id().index].dependents, vec![]);
+
+        // observers of a removed node hear about it on the next stabilization.
+        var.set(&mut dag, 2);
+        dag.stablize();
+        assert_eq!(
+            *updates.lock().unwrap(),
+            vec![Update::Initialized(2), Update::Invalidated]
+        );
+
+        let tripled = dag.map(var.as_input(), |x| x * 3);
+        assert_eq!(tripled.id().index, old_id.index);
+        assert_ne!(tripled.id(), old_id);
+        assert_eq!(dag.nodes.iter().filter(|slot| slot.live).count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "has been removed from the graph")]
+    fn test_stale_handle_is_detected() {
+        let mut dag = Incrementars::new();
+        let var = dag.var(1);
+        let doubled = dag.map(var.as_input(), |x| x * 2);
+        dag.remove(doubled.id());
+        dag.map(doubled.as_input(), |x| x + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "still depends on it")]
+    fn test_cannot_remove_node_with_dependents() {
+        let mut dag = Incrementars::new();
+        let var = dag.var(1);
+        dag.map(var.as_input(), |x| x * 2);
+        dag.remove(var.id());
+    }
+
+    #[test]
+    fn test_bind_builds_subgraph_in_scope() {
+        let mut dag = Incrementars::new();
+        let px = dag.var(10);
+        let venue = dag.var("lse");
+
+        let binder = dag.bind(venue.as_input(), move |venue, scope| {
+            let fee = if venue == "lse" { 1 } else { 2 };
+            let with_fee = scope.map(px.as_input(), move |px| px + fee);
+            scope.map(with_fee.as_input(), |x| x * 100).as_input()
+        });
+        let observer = dag.observe(binder.as_input());
+        let updates = record(&mut dag, observer);
+        dag.stablize();
+        assert_eq!(binder.observe(&dag), 1100);
+        let first_activation = dag.scopes[&binder.id()].clone();
+        assert_eq!(first_activation.len(), 2);
+
+        // changes under the picked subgraph flow through without rebuilding it.
+        px.set(&mut dag, 20);
+        dag.stablize();
+        assert_eq!(binder.observe(&dag), 2100);
+        assert_eq!(dag.scopes[&binder.id()], first_activation);
+
+        // a new input rebuilds, and the previous activation is reclaimed.
+        venue.set(&mut dag, "xetra");
+        dag.stablize();
+        assert_eq!(binder.observe(&dag), 2200);
+        assert!(first_activation.iter().all(|id| !dag.contains(*id)));
+        assert_eq!(dag.nodes[px.id().index].dependents.len(), 1);
+        assert_eq!(
+            *updates.lock().unwrap(),
+            vec![
+                Update::Initialized(1100),
+                Update::Changed {
+                    old: 1100,
+                    new: 2100
+                },
+                Update::Changed {
+                    old: 2100,
+                    new: 2200
+                },
+            ]
+        );
+
+        // slots of reclaimed nodes are reused, so the graph does not grow.
+        let live = dag.nodes.len();
+        for venue_name in ["lse", "xetra", "lse", "xetra"] {
+            venue.set(&mut dag, venue_name);
+            dag.stablize();
+        }
+        assert_eq!(dag.nodes.len(), live);
+    }
+
+    #[test]
+    fn test_nested_scopes_are_reclaimed() {
+        let mut dag = Incrementars::new();
+        let outer = dag.var(1);
+        let inner = dag.var(10);
+
+        let binder = dag.bind(outer.as_input(), move |outer, scope| {
+            let nested = scope.bind(inner.as_input(), move |inner, scope| {
+                scope.var(outer + inner).as_input()
+            });
+            nested.as_input()
+        });
+        dag.observe(binder.as_input());
+        dag.stablize();
+        assert_eq!(binder.observe(&dag), 11);
+        let nested_id = dag.scopes[&binder.id()][0];
+        let nested_scope = dag.scopes[&nested_id].clone();
+
+        outer.set(&mut dag, 2);
+        dag.stablize();
+        assert_eq!(binder.observe(&dag), 12);
+        assert!(!dag.contains(nested_id));
+        assert!(!dag.scopes.contains_key(&nested_id));
+        assert!(nested_scope.iter().all(|id| !dag.contains(*id)));
+    }
+
+    #[test]
+    fn test_bind2_routes_on_two_inputs() {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        enum Venue {
+            Lit,
+            Dark,
+        }
+
+        let mut dag = Incrementars::new();
+        let venue = dag.var(Venue::Lit);
+        let is_open = dag.var(true);
+        let lit_px = dag.var(100);
+        let dark_px = dag.var(99);
+        let closed = dag.var(0);
+        let lit_map = dag.map(lit_px.as_input(), |px| px * 2);
+
+        let routed = dag.bind2(
+            venue.as_input(),
+            is_open.as_input(),
+            move |venue, is_open, _| -> Input<i32> {
+                match (venue, is_open) {
+                    (_, false) => closed.as_input(),
+                    (Venue::Lit, true) => lit_map.as_input(),
+                    (Venue::Dark, true) => dark_px.as_input(),
+                }
+            },
+        );
+        let after = dag.map(routed.as_input(), |px| px + 1);
+        dag.observe(after.as_input());
+        assert_eq!(after.observe(&dag), 201);
+        assert_eq!(routed.height(&dag), lit_map.height(&dag) + 1);
+
+        is_open.set(&mut dag, false);
+        dag.stablize();
+        assert_eq!(after.observe(&dag), 1);
+        assert_eq!(dag.nodes[lit_map.id().index].dependents, vec![]);
+
+        is_open.set(&mut dag, true);
+        venue.set(&mut dag, Venue::Dark);
+        dag.stablize();
+        assert_eq!(after.observe(&dag), 100);
+        assert_eq!(dag.nodes[dark_px.id().index].dependents, vec![routed.id()]);
+
+        // picking a higher node pushes the bind and its dependents up.
+        venue.set(&mut dag, Venue::Lit);
+        dag.stablize();
+        assert_eq!(after.observe(&dag), 201);
+        assert_eq!(routed.height(&dag), lit_map.height(&dag) + 1);
+        assert_eq!(after.height(&dag), routed.height(&dag) + 1);
+    }
+
+    #[test]
+    fn test_bind3_builds_in_scope() {
+        let mut dag = Incrementars::new();
+        let venue = dag.var(1);
+        let product = dag.var(10);
+        let session = dag.var(100);
+        let px = dag.var(5);
+
+        let routed = dag.bind3(
+            venue.as_input(),
+            product.as_input(),
+            session.as_input(),
+            move |v, p, s, scope| {
+                let offset = v + p + s;
+                scope.map(px.as_input(), move |px| px + offset).as_input()
+            },
+        );
+        dag.observe(routed.as_input());
+        assert_eq!(routed.observe(&dag), 116);
+        let first = dag.scopes[&routed.id()].clone();
+
+        session.set(&mut dag, 200);
+        dag.stablize();
+        assert_eq!(routed.observe(&dag), 216);
+        assert!(first.iter().all(|id| !dag.contains(*id)));
+
+        px.set(&mut dag, 6);
+        dag.stablize();
+        assert_eq!(routed.observe(&dag), 217);
+    }
+
+    #[test]
+    fn test_bind_rewire_deepens_all_dependents() {
+        let mut dag = Incrementars::new();
+        let use_deep = dag.var(false);
+        let shallow = dag.var(1);
+        let mut deep = dag.map(shallow.as_input(), |x| x + 1);
+        for _ in 0..5 {
+            deep = dag.map(deep.as_input(), |x| x + 1);
+        }
+
+        let picked = dag.bind(use_deep.as_input(), move |use_deep, _| {
+            if use_deep {
+                deep.as_input()
+            } else {
+                shallow.as_input()
+            }
+        });
+        let once = dag.map(picked.as_input(), |x| x * 10);
+        let twice = dag.map(once.as_input(), |x| x + 1);
+        dag.observe(twice.as_input());
+
+        use_deep.set(&mut dag, true);
+        dag.stablize();
+        assert_eq!(twice.observe(&dag), 71);
+        assert_eq!(picked.height(&dag), deep.height(&dag) + 1);
+        assert_eq!(once.height(&dag), picked.height(&dag) + 1);
+        assert_eq!(twice.height(&dag), once.height(&dag) + 1);
+        assert_eq!(
+            dag.nodes[picked.id().index].inputs,
+            vec![use_deep.id(), deep.id()]
+        );
+    }
+
+    #[test]
+    fn test_chain_deeper_than_a_thousand() {
+        let mut dag = Incrementars::new();
+        let var = dag.var(0);
+        let mut map = dag.map(var.as_input(), |x| x + 1);
+        for _ in 0..2_500 {
+            map = dag.map(map.as_input(), |x| x + 1);
+        }
+        dag.observe(map.as_input());
+        assert_eq!(var.height(&dag), 0);
+        assert_eq!(map.height(&dag), 2_501);
+
+        var.set(&mut dag, 10);
+        dag.stablize();
+        assert_eq!(map.observe(&dag), 2_511);
+    }
+
+    #[test]
+    fn test_deep_graph_drops_without_recursion() {
+        // run on a small stack, so that anything recursing per node would overflow it.
+        std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(|| {
+                let mut dag = Incrementars::new();
+                let var = dag.var(0u64);
+                let mut map = dag.map(var, |x| x + 1);
+                for _ in 0..200_000 {
+                    map = dag.map(map, |x| x + 1);
+                }
+                dag.observe(map);
+                var.set(&mut dag, 1);
+                dag.stablize();
+                assert_eq!(map.observe(&dag), 200_002);
+                drop(dag);
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_bind_picking_its_own_input_switches_back() {
+        let mut dag = Incrementars::new();
+        let x = dag.var(1);
+        let y = dag.var(100);
+        let picked = dag.bind(x, move |value, _| {
+            if value > 0 {
+                x.as_input()
+            } else {
+                y.as_input()
+            }
+        });
+        let observer = dag.observe(picked);
+        // left unobserved, so the checker has an unnecessary node next to the bind to look at.
+        let doubled = dag.map(x, |value| value * 2);
+        dag.stablize();
+        assert_eq!(observer.value(&dag), 1);
+
+        x.set(&mut dag, -1);
+        dag.stablize();
+        assert_eq!(observer.value(&dag), 100);
+        assert_eq!(
+            doubled.try_observe(&dag),
+            Err(IncrError::Stale(doubled.id()))
+        );
+        dag.check_invariants();
+
+        // the bind still reads `x`, so it picks again.
+        x.set(&mut dag, 5);
+        dag.stablize();
+        assert_eq!(observer.value(&dag), 5);
+        dag.check_invariants();
+    }
+
+    #[test]
+    fn test_bind_picks_stale_node() {
+        let mut dag = Incrementars::new();
+        let use_fair = dag.var(false);
+        let last = dag.var(100);
+        let fair_px = dag.var(99);
+        let fair = dag.map(fair_px.as_input(), |px| px + 1);
+
+        let px = dag.bind(use_fair.as_input(), move |use_fair, _| {
+            if use_fair {
+                fair.as_input()
+            } else {
+                last.as_input()
+            }
+        });
+        let notional = dag.map(px.as_input(), |px| px * 10);
+        dag.observe(notional.as_input());
+        assert_eq!(notional.observe(&dag), 1000);
+
+        // `fair` misses this change, since nothing looks at it yet.
+        fair_px.set(&mut dag, 199);
+        dag.stablize();
+        assert_eq!(fair.try_observe(&dag), Err(IncrError::Stale(fair.id())));
+
+        // and this one happens in the same pass that picks it.
+        fair_px.set(&mut dag, 299);
+        use_fair.set(&mut dag, true);
+        dag.stablize();
+        assert_eq!(fair.observe(&dag), 300);
+        assert_eq!(notional.observe(&dag), 3000);
+        assert_eq!(notional.height(&dag), fair.height(&dag) + 2);
+    }
+
+    #[test]
+    fn test_slot_freed_mid_pass_is_reused_fresh() {
+        let mut dag = Incrementars::new();
+        let w = dag.var(1);
+        let mut deep = dag.map(w, |x| x * 100);
+        for _ in 0..8 {
+            deep = dag.map(deep, |x| x + 1);
+        }
+        let mut shallow = dag.map(w, |x| x);
+        for _ in 0..2 {
+            shallow = dag.map(shallow, |x| x);
+        }
+        // switching frees the node built by the previous activation, queued earlier in the pass.
+        let drop_scope = dag.var(false);
+        let b1 = dag.bind(drop_scope, move |drop_scope, scope| match drop_scope {
+            true => w.as_input(),
+            false => scope.map(w, |x| x + 1).as_input(),
+        });
+        let use_deep = dag.var(false);
+        let b2 = dag.bind(use_deep, move |use_deep, scope| match use_deep {
+            true => scope.map(deep, |x| x * 10).as_input(),
+            false => shallow.as_input(),
+        });
+        dag.observe(b1);
+        let observer = dag.observe(b2);
+        dag.stablize();
+        assert_eq!(
+            (deep.height(&dag), b1.height(&dag), b2.height(&dag)),
+            (9, 2, 4)
+        );
+
+        w.set(&mut dag, 2);
+        drop_scope.set(&mut dag, true);
+        use_deep.set(&mut dag, true);
+        dag.stablize();
+        assert_eq!(observer.value(&dag), 2080);
+    }
+
+    #[test]
+    fn test_borrowing_reads_do_not_clone() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        lazy_static! {
+            static ref CLONES: AtomicUsize = AtomicUsize::new(0);
+        }
+
+        #[derive(PartialEq)]
+        struct Book(Vec<u64>);
+
+        impl Clone for Book {
+            fn clone(&self) -> Self {
+                CLONES.fetch_add(1, Ordering::SeqCst);
+                Book(self.0.clone())
+            }
+        }
+
+        let mut dag = Incrementars::new();
+        let book = dag.var(Book(vec![1, 2, 3]));
+        let levels = dag.map_ref(book.as_input(), |b: &Book| b.0.len());
+        let live = dag.var(true);
+        let picked = dag.bind(live.as_input(), move |_, _| book.as_input());
+        let total = dag.map2_ref(picked.as_input(), levels.as_input(), |b, n| {
+            b.0.iter().sum::<u64>() + *n as u64
+        });
+        dag.observe(total.as_input());
+        assert_eq!(total.observe(&dag), 9);
+
+        book.update(&mut dag, |b| b.0.push(4));
+        dag.stablize();
+        assert_eq!(total.observe(&dag), 14);
+        assert_eq!(picked.with(&dag, |b| b.0.len()), 4);
+        assert_eq!(book.with(&dag, |b| b.0[0]), 1);
+        assert_eq!(CLONES.load(Ordering::SeqCst), 0);
+    }
+
+    /// A bind reading from `base` until `use_above` is set, when it picks `above`, which reads
+    /// from the bind through `middle`.
+    struct CycleFixture {
+        use_above: Var<bool>,
+        base: Var<i32>,
+        picked: Bind1<bool, i32>,
+        middle: Map1<i32, i32>,
+        above: Map1<i32, i32>,
+    }
+
+    fn cycle_fixture(dag: &mut Incrementars) -> CycleFixture {
+        let use_above = dag.var(false);
+        let base = dag.var(1);
+        let above_slot: Arc<Mutex<Option<Input<i32>>>> = Arc::default();
+        let slot = above_slot.clone();
+        let picked = dag.bind(use_above, move |use_above, _| {
+            if use_above {
+                slot.lock().unwrap().unwrap()
+            } else {
+                base.as_input()
+            }
+        });
+        let middle = dag.map(picked, |x| x + 1);
+        let above = dag.map(middle, |x| x * 2);
+        *above_slot.lock().unwrap() = Some(above.as_input());
+        CycleFixture {
+            use_above,
+            base,
+            picked,
+            middle,
+            above,
+        }
+    }
+
+    #[test]
+    fn test_bind_picking_its_dependent_is_a_cycle() {
+        let mut dag = Incrementars::new();
+        let CycleFixture {
+            use_above,
+            picked,
+            middle,
+            above,
+            ..
+        } = cycle_fixture(&mut dag);
+        dag.observe(above);
+
+        use_above.set(&mut dag, true);
+        let panic =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| dag.stablize())).unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        let path = format!(
+            "{:?} -> {:?} -> {:?} -> {:?}",
+            picked.id(),
+            middle.id(),
+            above.id(),
+            picked.id()
+        );
+        assert!(message.contains("would create a cycle"), "{message}");
+        assert!(message.ends_with(&path), "{message}");
+    }
+
+    #[test]
+    fn test_try_stablize_reports_cycle_and_recovers() {
+        let mut dag = Incrementars::new();
+        let CycleFixture {
+            use_above,
+            base,
+            picked,
+            middle,
+            above,
+        } = cycle_fixture(&mut dag);
+        let observer = dag.observe(above);
+        let updates = record(&mut dag, observer);
+        let other = dag.var(1);
+        let other_doubled = dag.map(other, |x| x * 2);
+        dag.observe(other_doubled);
+        assert_eq!(dag.try_stablize(), Ok(()));
+
+        use_above.set(&mut dag, true);
+        other.set(&mut dag, 5);
+        assert_eq!(
+            dag.try_stablize(),
+            Err(IncrError::Cycle(vec![picked.id(), middle.id(), above.id()]))
+        );
+
+        // the bind and everything downstream of it failed; the rest of the graph stabilized.
+        let cycle = IncrError::Cycle(vec![picked.id(), middle.id(), above.id()]);
+        assert_eq!(other_doubled.try_observe(&dag), Ok(10));
+        assert_eq!(above.try_observe(&dag), Err(cycle.clone()));
+        assert_eq!(dag.nodes[picked.id().index].inputs, vec![use_above.id()]);
+
+        other.set(&mut dag, 6);
+        assert_eq!(dag.try_stablize(), Ok(()));
+        assert_eq!(other_doubled.observe(&dag), 12);
+        assert_eq!(above.try_observe(&dag), Err(cycle.clone()));
+
+        // picking again once its input changes brings the bind back.
+        use_above.set(&mut dag, false);
+        base.set(&mut dag, 2);
+        assert_eq!(dag.try_stablize(), Ok(()));
+        assert_eq!(observer.value(&dag), 6);
+        assert_eq!(
+            *updates.lock().unwrap(),
+            vec![
+                Update::Initialized(4),
+                Update::Failed(cycle),
+                Update::Changed { old: 4, new: 6 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_try_map_failure_propagates_and_recovers() {
+        let mut dag = Incrementars::new();
+        let text = dag.var("2".to_string());
+        let divisor = dag.var(1);
+        let parsed = dag.try_map(text, |text| text.parse::<i32>());
+        let runs = Arc::new(Mutex::new(0));
+        let counter = runs.clone();
+        let doubled = dag.map(parsed, move |x| {
+            *counter.lock().unwrap() += 1;
+            x * 2
+        });
+        let ratio = dag.try_map2(doubled, divisor, |x, d| match d {
+            0 => Err("division by zero"),
+            d => Ok(x / d),
+        });
+        let observer = dag.observe(ratio);
+        let updates = record(&mut dag, observer);
+        dag.stablize();
+        assert_eq!(observer.value(&dag), 4);
+
+        // a failure reaches every dependent without running them.
+        text.set(&mut dag, "two".to_string());
+        dag.stablize();
+        let parse_error = IncrError::Failed {
+            node: parsed.id(),
+            message: "invalid digit found in string".to_string(),
+        };
+        assert_eq!(parsed.try_observe(&dag), Err(parse_error.clone()));
+        assert_eq!(doubled.try_observe(&dag), Err(parse_error.clone()));
+        assert_eq!(observer.error(&dag), Some(parse_error.clone()));
+        assert_eq!(*runs.lock().unwrap(), 1);
+
+        // recovering with the value from before the failure still clears it downstream.
+        text.set(&mut dag, "2".to_string());
+        dag.stablize();
+        assert_eq!(observer.error(&dag), None);
+        assert_eq!(observer.value(&dag), 4);
+
+        divisor.set(&mut dag, 0);
+        dag.stablize();
+        let division_error = IncrError::Failed {
+            node: ratio.id(),
+            message: "division by zero".to_string(),
+        };
+        assert_eq!(observer.error(&dag), Some(division_error.clone()));
+        assert_eq!(doubled.try_observe(&dag), Ok(4));
+
+        divisor.set(&mut dag, 2);
+        dag.stablize();
+        assert_eq!(
+            *updates.lock().unwrap(),
+            vec![
+                Update::Initialized(4),
+                Update::Failed(parse_error),
+                Update::Changed { old: 4, new: 4 },
+                Update::Failed(division_error),
+                Update::Changed { old: 4, new: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nodes_built_on_failed_input_start_failed() {
+        let mut dag = Incrementars::new();
+        let text = dag.var("x".to_string());
+        let parsed = dag.try_map(text, |text| text.parse::<i32>());
+        let incremented = dag.map(parsed, |x| x + 1);
+        let fallback = dag.var(0);
+        let picked = dag.bind(parsed, move |x, _| match x {
+            0 => fallback.as_input(),
+            _ => incremented.as_input(),
+        });
+        let observer = dag.observe(picked);
+        dag.stablize();
+        let error = IncrError::Failed {
+            node: parsed.id(),
+            message: "invalid digit found in string".to_string(),
+        };
+        assert_eq!(incremented.try_observe(&dag), Err(error.clone()));
+        assert_eq!(picked.try_observe(&dag), Err(error.clone()));
+        assert_eq!(observer.error(&dag), Some(error));
+
+        text.set(&mut dag, "4".to_string());
+        dag.stablize();
+        assert_eq!(observer.value(&dag), 5);
+        text.set(&mut dag, "0".to_string());
+        dag.stablize();
+        assert_eq!(observer.value(&dag), 0);
+    }
+
+    #[test]
+    fn test_bind_picking_failed_node_fails() {
+        let mut dag = Incrementars::new();
+        let text = dag.var("x".to_string());
+        let parsed = dag.try_map(text, |text| text.parse::<i32>());
+        let fallback = dag.var(1);
+        let use_parsed = dag.var(false);
+        let picked = dag.bind(use_parsed, move |use_parsed, _| match use_parsed {
+            true => parsed.as_input(),
+            false => fallback.as_input(),
+        });
+        let incremented = dag.map(picked, |x| x + 1);
+        dag.observe(incremented);
+        dag.stablize();
+        assert_eq!(incremented.observe(&dag), 2);
+
+        use_parsed.set(&mut dag, true);
+        dag.stablize();
+        assert_eq!(
+            incremented.try_observe(&dag),
+            Err(IncrError::Failed {
+                node: parsed.id(),
+                message: "invalid digit found in string".to_string(),
+            })
+        );
+
+        text.set(&mut dag, "5".to_string());
+        dag.stablize();
+        assert_eq!(incremented.observe(&dag), 6);
+    }
+
+    #[test]
+    fn test_check_invariants() {
+        let mut dag = Incrementars::new();
+        let x = dag.var(1);
+        let doubled = dag.map(x, |x| x * 2);
+        let sum = dag.map2(x, doubled, |x, d| x + d);
+        dag.observe(sum);
+        dag.stablize();
+        dag.check_invariants();
+
+        // pending work is fine between stabilizations, as long as it is queued.
+        x.set(&mut dag, 2);
+        dag.check_invariants();
+        dag.stablize();
+
+        dag.nodes[sum.id().index].height = 1;
+        let panic =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| dag.check_invariants()))
+                .unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert_eq!(
+            message,
+            &format!(
+                "node {:?} at height 1 is not above its input {:?} at height 1",
+                sum.id(),
+                doubled.id()
+            )
+        );
+        dag.nodes[sum.id().index].height = 2;
+
+        // as if `doubled` changed without `sum` recomputing, like a missed enqueue would leave it.
+        dag.nodes[doubled.id().index].changed_at_step = dag.steps + 1;
+        let panic =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| dag.check_invariants()))
+                .unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert_eq!(
+            message,
+            &format!(
+                "node {:?} has not recomputed since its input {:?} changed",
+                sum.id(),
+                doubled.id()
+            )
+        );
+    }
+
+    #[test]
+    fn test_caught_panic_poisons_node_and_dependents() {
+        let mut dag = Incrementars::new();
+        dag.set_catch_panics(true);
+        let divisor = dag.var(1);
+        let quotient = dag.map(divisor, |d| 100 / d);
+        let incremented = dag.map(quotient, |x| x + 1);
+        let observer = dag.observe(incremented);
+        let updates = record(&mut dag, observer);
+        let other = dag.var(1);
+        let other_doubled = dag.map(other, |x| x * 2);
+        dag.observe(other_doubled);
+        dag.stablize();
+
+        divisor.set(&mut dag, 0);
+        other.set(&mut dag, 2);
+        dag.stablize();
+        let error = IncrError::Panicked {
+            node: quotient.id(),
+            message: "attempt to divide by zero".to_string(),
+        };
+        assert_eq!(incremented.try_observe(&dag), Err(error.clone()));
+        assert_eq!(other_doubled.observe(&dag), 4);
+        assert!(dag.dirty.is_empty());
+
+        divisor.set(&mut dag, 4);
+        dag.stablize();
+        assert_eq!(
+            *updates.lock().unwrap(),
+            vec![
+                Update::Initialized(101),
+                Update::Failed(error),
+                Update::Changed { old: 101, new: 26 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_caught_panic_in_handler_skips_it() {
+        let mut dag = Incrementars::new();
+        dag.set_catch_panics(true);
+        let input = dag.var(1);
+        let observer = dag.observe(input);
+        observer.on_update(&mut dag, |update| {
+            if matches!(update, Update::Changed { new: 2, .. }) {
+                panic!("cannot handle 2");
+            }
+        });
+        let updates = record(&mut dag, observer);
+        dag.stablize();
+
+        input.set(&mut dag, 2);
+        dag.stablize();
+        input.set(&mut dag, 3);
+        dag.stablize();
+        dag.remove(input.id());
+        dag.stablize();
+        assert_eq!(
+            *updates.lock().unwrap(),
+            vec![
+                Update::Initialized(1),
+                Update::Changed { old: 1, new: 2 },
+                Update::Changed { old: 2, new: 3 },
+                Update::Invalidated,
+            ]
+        );
+        assert!(!dag.contains(observer.id()));
+    }
+
+    #[test]
+    fn test_panicking_bind_keeps_previous_activation() {
+        let mut dag = Incrementars::new();
+        dag.set_catch_panics(true);
+        let input = dag.var(1);
+        let picked = dag.bind(input, |x, scope| {
+            let built = scope.var(x * 10);
+            if x == 2 {
+                panic!("cannot pick for {x}");
+            }
+            built.as_input()
+        });
+        let observer = dag.observe(picked);
+        dag.stablize();
+        let built = dag.scopes[&picked.id()].clone();
+
+        input.set(&mut dag, 2);
+        dag.stablize();
+        assert_eq!(
+            observer.error(&dag),
+            Some(IncrError::Panicked {
+                node: picked.id(),
+                message: "cannot pick for 2".to_string(),
+            })
+        );
+        // nodes from the failed run are gone; the previous ones stay until the next pick.
+        assert_eq!(dag.scopes[&picked.id()], built);
+        assert!(dag.contains(built[0]));
+        assert_eq!(dag.current_scope, None);
+        assert_eq!(dag.free.len(), 1);
+
+        input.set(&mut dag, 3);
+        dag.stablize();
+        assert_eq!(observer.value(&dag), 30);
+        assert!(!dag.contains(built[0]));
+    }
+
+    #[test]
+    fn test_uncaught_panic_leaves_graph_usable() {
+        let mut dag = Incrementars::new();
+        let divisor = dag.var(1);
+        let quotient = dag.map(divisor, |d| 100 / d);
+        let incremented = dag.map(quotient, |x| x + 1);
+        let quotient_observer = dag.observe(quotient);
+        let observer = dag.observe(incremented);
+        let other = dag.var(1);
+        let doubled = dag.map(other, |x| x * 2);
+        let tripled = dag.map(doubled, |x| x * 3);
+        let tripled_observer = dag.observe(tripled);
+        dag.stablize();
+
+        divisor.set(&mut dag, 0);
+        other.set(&mut dag, 2);
+        let panicked = std::panic::catch_unwind(AssertUnwindSafe(|| dag.stablize()));
+        assert!(panicked.is_err());
+        // the panicking node is back in its slot, and nothing is left queued.
+        assert_eq!(quotient_observer.value(&dag), 100);
+        assert!(dag.queue.pop().is_none());
+        assert!(!dag.stabilizing);
+        dag.check_invariants();
+
+        divisor.set(&mut dag, 4);
+        dag.stablize();
+        assert_eq!(observer.value(&dag), 26);
+        assert_eq!(tripled_observer.value(&dag), 12);
+    }
+
+    #[test]
+    fn test_set_queues_var_once() {
+        let mut dag = Incrementars::new();
+        let bid = dag.var(10);
+        let ask = dag.var(12);
+        let mid = dag.map2(bid.as_input(), ask.as_input(), |b, a| (b + a) / 2);
+        dag.observe(mid.as_input());
+
+        bid.set(&mut dag, 11);
+        bid.set(&mut dag, 12);
+        assert_eq!(dag.dirty, vec![bid.id()]);
+        dag.stablize();
+        assert!(dag.dirty.is_empty());
+        assert_eq!(mid.observe(&dag), 12);
+
+        // a var removed while queued is skipped.
+        let unused = dag.var(0);
+        unused.set(&mut dag, 1);
+        dag.remove(unused.id());
+        dag.stablize();
+        assert!(dag.dirty.is_empty());
+    }
+
+    #[test]
+    fn test_recompute_heap_pops_lowest_height_first() {
+        let id = |index| NodeId {
+            index,
+            generation: 0,
+        };
+        let mut heap = RecomputeHeap::new();
+        heap.push(3, id(0));
+        heap.push(1, id(1));
+        heap.push(3, id(2));
+        assert_eq!(heap.pop(), Some((1, id(1))));
+        // heights may go back down while the heap is being drained.
+        heap.push(0, id(3));
+        assert_eq!(heap.pop(), Some((0, id(3))));
+        let mut rest = vec![heap.pop().unwrap(), heap.pop().unwrap()];
+        rest.sort();
+        assert_eq!(rest, vec![(3, id(0)), (3, id(2))]);
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_stablize_on_another_thread() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let mut dag = Incrementars::new();
+        let qty = dag.var(3);
+        let px = dag.var(100);
+        let notional = dag.map2(qty, px, |q, p| q * p);
+        let observer = dag.observe(notional);
+        assert_send_sync(&dag);
+        assert_send_sync(&observer);
+
+        let dag = std::thread::spawn(move || {
+            qty.set(&mut dag, 4);
+            dag.stablize();
+            dag
+        })
+        .join()
+        .unwrap();
+        assert_eq!(observer.value(&dag), 400);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_shared_readers_see_stabilized_values() {
+        let dag = SharedIncrementars::new();
+        let x = dag.var(0);
+        let doubled = dag.map(x, |x| x * 2);
+        let both = dag.map2(x, doubled, |x, d| (x, d));
+        dag.observe(both);
+        dag.stablize();
+
+        // a set var is not visible before the stabilization that applies it.
+        dag.set(x, 1);
+        assert_eq!(dag.value(x), 0);
+        dag.stablize();
+        assert_eq!(dag.value(both), (1, 2));
+
+        std::thread::scope(|threads| {
+            for _ in 0..4 {
+                let dag = dag.clone();
+                threads.spawn(move || loop {
+                    let (x, d, pair) =
+                        dag.read(|dag| (x.observe(dag), doubled.observe(dag), both.observe(dag)));
+                    assert_eq!(d, x * 2);
+                    assert_eq!(pair, (x, d));
+                    if x == 100 {
+                        break;
+                    }
+                });
+            }
+            for i in 1..=100 {
+                dag.set(x, i);
+                dag.stablize();
+            }
+        });
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_shared_reentrant_calls_are_caught() {
+        let dag = SharedIncrementars::new();
+        let x = dag.var(1);
+        let seen = dag.var(0);
+        let attempts = Arc::new(Mutex::new(vec![]));
+        let (inner, sink) = (dag.clone(), attempts.clone());
+        let doubled = dag.map(x, move |x| {
+            inner.set(seen, x);
+            sink.lock().unwrap().push(inner.try_stablize());
+            x * 2
+        });
+        dag.observe(doubled);
+        assert_eq!(dag.value(seen), 0);
+
+        // sets from inside a build or a stabilization wait for the next stabilization.
+        dag.stablize();
+        assert_eq!(dag.value(seen), 1);
+        dag.set(x, 5);
+        dag.stablize();
+        assert_eq!(dag.value(doubled), 10);
+        assert_eq!(dag.value(seen), 1);
+        dag.stablize();
+        assert_eq!(dag.value(seen), 5);
+        assert_eq!(
+            *attempts.lock().unwrap(),
+            vec![Err(IncrError::Reentrant), Err(IncrError::Reentrant)]
+        );
+
+        let panic = std::panic::catch_unwind(|| dag.write(|_| dag.value(x))).unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().unwrap(),
+            &IncrError::Reentrant.to_string()
+        );
+        // the panic above unwound while the graph was held, which must not break later calls.
+        assert_eq!(dag.value(x), 5);
+        dag.set(x, 6);
+        assert_eq!(dag.try_stablize(), Ok(()));
+        assert_eq!(dag.value(doubled), 12);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_uncaught_panic_in_parallel_stablize_leaves_graph_usable() {
+        let mut dag = Incrementars::new();
+        let divisor = dag.var(1);
+        let quotients: Vec<_> = (0..8)
+            .map(|i| dag.map(divisor, move |d| (100 + i) / d))
+            .collect();
+        let observers: Vec<_> = quotients.iter().map(|q| dag.observe(*q)).collect();
+        dag.stablize_parallel(4);
+
+        divisor.set(&mut dag, 0);
+        let panicked = std::panic::catch_unwind(AssertUnwindSafe(|| dag.stablize_parallel(4)));
+        assert!(panicked.is_err());
+        assert_eq!(observers[7].value(&dag), 107);
+        dag.check_invariants();
+
+        divisor.set(&mut dag, 2);
+        dag.stablize_parallel(4);
+        let values: Vec<_> = observers.iter().map(|o| o.value(&dag)).collect();
+        assert_eq!(values, vec![50, 50, 51, 51, 52, 52, 53, 53]);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_parallel_stablize_matches_serial() {
+        use std::collections::HashSet;
+        use std::thread::ThreadId;
+
+        type Threads = Arc<Mutex<HashSet<ThreadId>>>;
+
+        fn build(
+            dag: &mut Incrementars,
+            threads: Threads,
+        ) -> (Var<i64>, Var<bool>, Vec<Observer<i64>>) {
+            let root = dag.var(1);
+            let use_left = dag.var(true);
+            let mut leaves = vec![dag.map(root, |x| x + 1)];
+            // wide enough for the last level to be split across threads.
+            for i in 0..10 {
+                leaves = leaves
+                    .into_iter()
+                    .flat_map(|leaf| {
+                        let threads = threads.clone();
+                        let doubled = dag.map(leaf, move |x| {
+                            threads.lock().unwrap().insert(std::thread::current().id());
+                            x * 2 + i
+                        });
+                        [doubled, dag.map(leaf, move |x| x * 3 - i)]
+                    })
+                    .collect();
+            }
+            let mut joined: Vec<Input<i64>> = leaves.iter().map(|leaf| leaf.as_input()).collect();
+            while joined.len() > 1 {
+                joined = joined
+                    .chunks(2)
+                    .map(|pair| dag.map2(pair[0], pair[1], |x, y| x + y).as_input())
+                    .collect();
+            }
+            let (left, right) = (leaves[0], leaves[leaves.len() - 1]);
+            let picked = dag.bind(use_left, move |use_left, scope| {
+                if use_left {
+                    left.as_input()
+                } else {
+                    scope.map(right, |x| -x).as_input()
+                }
+            });
+
+            let mut observers: Vec<_> = leaves.iter().map(|leaf| dag.observe(*leaf)).collect();
+            observers.push(dag.observe(joined[0]));
+            observers.push(dag.observe(picked));
+            (root, use_left, observers)
+        }
+
+        let threads = Threads::default();
+        let mut serial = Incrementars::new();
+        let mut parallel = Incrementars::new();
+        let (serial_root, serial_use_left, serial_observers) =
+            build(&mut serial, Threads::default());
+        let (root, use_left, observers) = build(&mut parallel, threads.clone());
+        serial.stablize();
+        parallel.stablize_parallel(4);
+
+        for (x, left) in [(2, true), (5, false), (5, true), (-3, false)] {
+            serial_root.set(&mut serial, x);
+            serial_use_left.set(&mut serial, left);
+            serial.stablize();
+            root.set(&mut parallel, x);
+            use_left.set(&mut parallel, left);
+            parallel.stablize_parallel(4);
+
+            let expected: Vec<_> = serial_observers.iter().map(|o| o.value(&serial)).collect();
+            let actual: Vec<_> = observers.iter().map(|o| o.value(&parallel)).collect();
+            assert_eq!(actual, expected);
+        }
+        assert!(threads.lock().unwrap().len() > 1);
+    }
 }
@@ -0,0 +1,126 @@
+use std::marker::PhantomData;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use super::error::IncrError;
+use super::id::{Input, NodeId};
+use super::scope::Scope;
+use super::traits::{
+    sync_dyn, Node, NodeValue, Notify, Observable, SendSync, StablizationCallback,
+};
+use super::Incrementars;
+
+/// An event delivered to [`Observer::on_update`] handlers at the end of a stabilization.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Update<T> {
+    /// First value seen by the observer.
+    Initialized(T),
+    /// The observed value changed since the last stabilization.
+    Changed { old: T, new: T },
+    /// The observed node failed. The next value it produces arrives as a change from the last
+    /// value seen before the failure.
+    Failed(IncrError),
+    /// The observed node was invalidated and will not produce further updates.
+    Invalidated,
+}
+
+type Handler<T> = Box<sync_dyn!(FnMut(Update<T>))>;
+
+/// Internal representation of an Observer node.
+pub struct _Observer<T> {
+    pub input: Input<T>,
+    // last value handed to the handlers, `None` until the observer is initialized.
+    pub last: Option<T>,
+    pub handlers: Vec<Handler<T>>,
+}
+
+impl<T: Clone + SendSync + 'static> Node for _Observer<T> {
+    fn stablize(&mut self, _: &mut Scope) -> Option<StablizationCallback> {
+        Some(StablizationCallback::NotifyObservers)
+    }
+    fn inputs(&self) -> Vec<NodeId> {
+        vec![self.input.id()]
+    }
+    fn value(&self) -> NodeValue<'_> {
+        NodeValue::At(self.input.id())
+    }
+    fn as_observer(&mut self) -> Option<&mut dyn Notify> {
+        Some(self)
+    }
+}
+
+impl<T: Clone> _Observer<T> {
+    /// Hands `update` to every handler. If the graph catches panics, a panicking handler does not
+    /// keep the others from running.
+    fn run_handlers(&mut self, dag: &Incrementars, update: Update<T>) {
+        self.handlers.iter_mut().for_each(|handler| {
+            if dag.catch_panics {
+                let _ = catch_unwind(AssertUnwindSafe(|| (handler)(update.clone())));
+            } else {
+                (handler)(update.clone());
+            }
+        });
+    }
+}
+
+impl<T: Clone + 'static> Notify for _Observer<T> {
+    fn notify(&mut self, dag: &Incrementars) {
+        let new = match self.input.try_observe(dag) {
+            Ok(new) => new,
+            Err(err) => return self.run_handlers(dag, Update::Failed(err)),
+        };
+        let update = match self.last.replace(new.clone()) {
+            None => Update::Initialized(new),
+            Some(old) => Update::Changed { old, new },
+        };
+        self.run_handlers(dag, update);
+    }
+
+    fn invalidate(&mut self, dag: &Incrementars) {
+        self.run_handlers(dag, Update::Invalidated);
+    }
+}
+
+/// A handle keeping track of a node's value across stabilizations.
+pub struct Observer<T> {
+    id: NodeId,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Observer<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Observer<T> {}
+
+impl<T: Clone + SendSync + 'static> Observer<T> {
+    pub(super) fn new(id: NodeId) -> Self {
+        Self {
+            id,
+            _value: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Current value of the observed node. Panics if it failed.
+    pub fn value(&self, dag: &Incrementars) -> T {
+        dag.read::<T>(self.id).clone()
+    }
+
+    /// Why the observed node failed, if it did.
+    pub fn error(&self, dag: &Incrementars) -> Option<IncrError> {
+        dag.error(self.id).cloned()
+    }
+
+    /// Registers a handler called at the end of every stabilization that changes the observed
+    /// value. Handlers only see updates that happen after they are registered.
+    pub fn on_update(&self, dag: &mut Incrementars, f: impl FnMut(Update<T>) + SendSync + 'static) {
+        dag.node_mut::<_Observer<T>>(self.id)
+            .handlers
+            .push(Box::new(f));
+    }
+}
@@ -0,0 +1,75 @@
+use super::id::NodeId;
+
+/// Nodes waiting to recompute, bucketed by height. Pops always come from the lowest non-empty
+/// height, so pushing and popping are O(1) as long as heights only grow during a stabilization.
+///
+/// Buckets are intrusive linked lists threaded through `next`, so a node must not be pushed again
+/// before it is popped.
+pub struct RecomputeHeap {
+    // first node queued at each height.
+    heads: Vec<Option<NodeId>>,
+    // key is node index, value is the node queued after it at the same height.
+    next: Vec<Option<NodeId>>,
+    // no height below this one holds a node.
+    min_height: usize,
+    len: usize,
+}
+
+impl RecomputeHeap {
+    pub fn new() -> Self {
+        RecomputeHeap {
+            heads: vec![],
+            next: vec![],
+            min_height: 0,
+            len: 0,
+        }
+    }
+
+    /// Makes room for a node at `height`, so queueing it later does not need to grow anything.
+    pub fn reserve(&mut self, height: usize, id: NodeId) {
+        if height >= self.heads.len() {
+            self.heads.resize(height + 1, None);
+        }
+        if id.index >= self.next.len() {
+            self.next.resize(id.index + 1, None);
+        }
+    }
+
+    pub fn push(&mut self, height: usize, id: NodeId) {
+        self.reserve(height, id);
+        self.next[id.index] = self.heads[height].replace(id);
+        self.min_height = if self.len == 0 {
+            height
+        } else {
+            self.min_height.min(height)
+        };
+        self.len += 1;
+    }
+
+    /// Removes a node at the lowest height, returning it along with the height it was queued at.
+    pub fn pop(&mut self) -> Option<(usize, NodeId)> {
+        if self.len == 0 {
+            return None;
+        }
+        while self.heads[self.min_height].is_none() {
+            self.min_height += 1;
+        }
+        let id = self.heads[self.min_height].take().unwrap();
+        self.heads[self.min_height] = self.next[id.index].take();
+        self.len -= 1;
+        Some((self.min_height, id))
+    }
+
+    /// Moves every node at the lowest height into `level`, returning that height.
+    #[cfg(feature = "sync")]
+    pub fn pop_level(&mut self, level: &mut Vec<NodeId>) -> Option<usize> {
+        let (height, id) = self.pop()?;
+        level.push(id);
+        while let Some(id) = self.heads[height].take() {
+            self.heads[height] = self.next[id.index].take();
+            self.len -= 1;
+            level.push(id);
+        }
+        Some(height)
+    }
+}
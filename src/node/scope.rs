@@ -0,0 +1,216 @@
+use std::fmt::Display;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+
+use super::id::{Input, NodeId};
+use super::{Bind1, Cutoff, Incrementars, Map1, Map2, Observable, SendSync, TryMap1, TryMap2, Var};
+
+/// Handed to the function of a bind so it can build new nodes. Everything built through a scope
+/// belongs to the activation of the bind that is running, and is invalidated and removed from the
+/// graph once that bind fires again.
+pub struct Scope<'g> {
+    dag: Access<'g>,
+}
+
+enum Access<'g> {
+    Exclusive(&'g mut Incrementars),
+    // nodes recomputing off the stabilizing thread can only read.
+    #[cfg(feature = "sync")]
+    Shared(&'g Incrementars),
+}
+
+impl<'g> Scope<'g> {
+    pub(super) fn new(dag: &'g mut Incrementars) -> Self {
+        Self {
+            dag: Access::Exclusive(dag),
+        }
+    }
+
+    /// A scope for a node that does not change the graph, which several threads can hold at once.
+    #[cfg(feature = "sync")]
+    pub(super) fn shared(dag: &'g Incrementars) -> Self {
+        Self {
+            dag: Access::Shared(dag),
+        }
+    }
+
+    fn dag(&self) -> &Incrementars {
+        match &self.dag {
+            Access::Exclusive(dag) => dag,
+            #[cfg(feature = "sync")]
+            Access::Shared(dag) => dag,
+        }
+    }
+
+    pub(super) fn dag_mut(&mut self) -> &mut Incrementars {
+        match &mut self.dag {
+            Access::Exclusive(dag) => dag,
+            #[cfg(feature = "sync")]
+            Access::Shared(_) => panic!("only nodes that change the graph can build nodes"),
+        }
+    }
+
+    /// Current value of `id`.
+    pub(super) fn read<T: 'static>(&self, id: NodeId) -> &T {
+        self.dag().read(id)
+    }
+
+    /// Runs `f` as a fresh activation of the bind `owner`. Nodes from the previous activation are
+    /// retired, and removed by the graph once the bind's new dependencies are in place. If `f`
+    /// panics, the previous activation stays and the nodes built so far are retired instead.
+    pub(super) fn activate<R>(&mut self, owner: NodeId, f: impl FnOnce(&mut Scope) -> R) -> R {
+        let dag = self.dag_mut();
+        let previous = dag.scopes.insert(owner, vec![]).unwrap_or_default();
+        let outer = dag.current_scope.replace(owner);
+        let result = catch_unwind(AssertUnwindSafe(|| (f)(self)));
+        let dag = self.dag_mut();
+        dag.current_scope = outer;
+        match result {
+            Ok(result) => {
+                dag.retired.extend(previous);
+                result
+            }
+            Err(payload) => {
+                let built = dag.scopes.insert(owner, previous).unwrap_or_default();
+                dag.retired.extend(built);
+                resume_unwind(payload)
+            }
+        }
+    }
+
+    /// The stabilization in which `id` last changed its value.
+    pub(super) fn changed_at(&self, id: NodeId) -> u64 {
+        self.dag().nodes[id.index].changed_at
+    }
+
+    pub fn var<T: Clone + SendSync + 'static>(&mut self, value: T) -> Var<T> {
+        self.dag_mut().var(value)
+    }
+
+    pub fn map<I: Clone + 'static, O: PartialEq + SendSync + 'static>(
+        &mut self,
+        input: impl Observable<I>,
+        f: impl Fn(I) -> O + SendSync + 'static,
+    ) -> Map1<I, O> {
+        self.dag_mut().map(input, f)
+    }
+
+    pub fn map_with_cutoff<I: Clone + 'static, O: SendSync + 'static>(
+        &mut self,
+        input: impl Observable<I>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(I) -> O + SendSync + 'static,
+    ) -> Map1<I, O> {
+        self.dag_mut().map_with_cutoff(input, cutoff, f)
+    }
+
+    pub fn map_ref<I: 'static, O: PartialEq + SendSync + 'static>(
+        &mut self,
+        input: impl Observable<I>,
+        f: impl Fn(&I) -> O + SendSync + 'static,
+    ) -> Map1<I, O> {
+        self.dag_mut().map_ref(input, f)
+    }
+
+    pub fn map_ref_with_cutoff<I: 'static, O: SendSync + 'static>(
+        &mut self,
+        input: impl Observable<I>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(&I) -> O + SendSync + 'static,
+    ) -> Map1<I, O> {
+        self.dag_mut().map_ref_with_cutoff(input, cutoff, f)
+    }
+
+    pub fn map2<I1: Clone + 'static, I2: Clone + 'static, O: PartialEq + SendSync + 'static>(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        f: impl Fn(I1, I2) -> O + SendSync + 'static,
+    ) -> Map2<I1, I2, O> {
+        self.dag_mut().map2(input1, input2, f)
+    }
+
+    pub fn map2_with_cutoff<I1: Clone + 'static, I2: Clone + 'static, O: SendSync + 'static>(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(I1, I2) -> O + SendSync + 'static,
+    ) -> Map2<I1, I2, O> {
+        self.dag_mut().map2_with_cutoff(input1, input2, cutoff, f)
+    }
+
+    pub fn map2_ref<I1: 'static, I2: 'static, O: PartialEq + SendSync + 'static>(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        f: impl Fn(&I1, &I2) -> O + SendSync + 'static,
+    ) -> Map2<I1, I2, O> {
+        self.dag_mut().map2_ref(input1, input2, f)
+    }
+
+    pub fn map2_ref_with_cutoff<I1: 'static, I2: 'static, O: SendSync + 'static>(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(&I1, &I2) -> O + SendSync + 'static,
+    ) -> Map2<I1, I2, O> {
+        self.dag_mut()
+            .map2_ref_with_cutoff(input1, input2, cutoff, f)
+    }
+
+    pub fn try_map<I: Clone + 'static, O: PartialEq + SendSync + 'static, E: Display>(
+        &mut self,
+        input: impl Observable<I>,
+        f: impl Fn(I) -> Result<O, E> + SendSync + 'static,
+    ) -> TryMap1<I, O> {
+        self.dag_mut().try_map(input, f)
+    }
+
+    pub fn try_map_with_cutoff<I: Clone + 'static, O: SendSync + 'static, E: Display>(
+        &mut self,
+        input: impl Observable<I>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(I) -> Result<O, E> + SendSync + 'static,
+    ) -> TryMap1<I, O> {
+        self.dag_mut().try_map_with_cutoff(input, cutoff, f)
+    }
+
+    pub fn try_map2<
+        I1: Clone + 'static,
+        I2: Clone + 'static,
+        O: PartialEq + SendSync + 'static,
+        E: Display,
+    >(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        f: impl Fn(I1, I2) -> Result<O, E> + SendSync + 'static,
+    ) -> TryMap2<I1, I2, O> {
+        self.dag_mut().try_map2(input1, input2, f)
+    }
+
+    pub fn try_map2_with_cutoff<
+        I1: Clone + 'static,
+        I2: Clone + 'static,
+        O: SendSync + 'static,
+        E: Display,
+    >(
+        &mut self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(I1, I2) -> Result<O, E> + SendSync + 'static,
+    ) -> TryMap2<I1, I2, O> {
+        self.dag_mut()
+            .try_map2_with_cutoff(input1, input2, cutoff, f)
+    }
+
+    pub fn bind<I: Clone + 'static, O: 'static>(
+        &mut self,
+        input: impl Observable<I>,
+        f: impl Fn(I, &mut Scope) -> Input<O> + SendSync + 'static,
+    ) -> Bind1<I, O> {
+        self.dag_mut().bind(input, f)
+    }
+}
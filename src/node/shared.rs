@@ -0,0 +1,172 @@
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockWriteGuard};
+use std::thread::{self, ThreadId};
+
+use super::error::IncrError;
+use super::id::Input;
+use super::{Bind1, Cutoff, Incrementars, Map1, Map2, Observable, Observer, Scope, SendSync, Var};
+
+type PendingSet = Box<dyn FnOnce(&mut Incrementars) + Send>;
+
+/// A graph that can be shared across threads. Reads share a lock, while building nodes and
+/// stabilizing take it exclusively. Sets are held back until the next [`stablize`], so readers
+/// only ever see the graph as of a finished stabilization.
+///
+/// Node functions and handlers may hold on to a clone of the graph. Sets they make wait for the
+/// next stabilization like any other, while reading, building or stabilizing from inside a
+/// stabilization or a build on the same thread fails with [`IncrError::Reentrant`] rather than
+/// deadlocking.
+///
+/// A panic while the graph is held does not poison it for later calls. The graph puts back what a
+/// stabilization cut short by a panic was doing, so the next one picks it up.
+///
+/// [`stablize`]: SharedIncrementars::stablize
+#[derive(Clone, Default)]
+pub struct SharedIncrementars {
+    dag: Arc<RwLock<Incrementars>>,
+    // sets waiting for the next stabilization, in the order they were made.
+    pending: Arc<Mutex<Vec<PendingSet>>>,
+    // thread holding the graph exclusively, if any.
+    writer: Arc<Mutex<Option<ThreadId>>>,
+}
+
+/// Exclusive access to the graph, marking the thread that holds it until dropped.
+struct Exclusive<'a> {
+    dag: RwLockWriteGuard<'a, Incrementars>,
+    writer: &'a Mutex<Option<ThreadId>>,
+}
+
+impl Drop for Exclusive<'_> {
+    fn drop(&mut self) {
+        // cleared before the lock is released, so it never overwrites the next writer.
+        *self.writer.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+impl SharedIncrementars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `f` on the graph as of the last stabilization. Everything `f` reads comes from the
+    /// same stabilization.
+    pub fn read<R>(&self, f: impl FnOnce(&Incrementars) -> R) -> R {
+        if let Err(err) = self.check_reentry() {
+            panic!("{}", err);
+        }
+        (f)(&self.dag.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Runs `f` with exclusive access to the graph. Changes made here are visible to readers right
+    /// away, even before the next stabilization.
+    pub fn write<R>(&self, f: impl FnOnce(&mut Incrementars) -> R) -> R {
+        match self.exclusive() {
+            Ok(mut exclusive) => (f)(&mut exclusive.dag),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Fails if this thread already holds the graph exclusively, where taking the lock again
+    /// would deadlock.
+    fn check_reentry(&self) -> Result<(), IncrError> {
+        let writer = *self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        if writer == Some(thread::current().id()) {
+            return Err(IncrError::Reentrant);
+        }
+        Ok(())
+    }
+
+    fn exclusive(&self) -> Result<Exclusive<'_>, IncrError> {
+        self.check_reentry()?;
+        let dag = self.dag.write().unwrap_or_else(PoisonError::into_inner);
+        *self.writer.lock().unwrap_or_else(PoisonError::into_inner) = Some(thread::current().id());
+        Ok(Exclusive {
+            dag,
+            writer: &self.writer,
+        })
+    }
+
+    /// Current value of `node`.
+    pub fn value<T: Clone + 'static>(&self, node: impl Observable<T>) -> T {
+        self.read(|dag| node.observe(dag))
+    }
+
+    pub fn var<T: Clone + SendSync + 'static>(&self, value: T) -> Var<T> {
+        self.write(|dag| dag.var(value))
+    }
+
+    pub fn map<I: Clone + 'static, O: PartialEq + SendSync + 'static>(
+        &self,
+        input: impl Observable<I>,
+        f: impl Fn(I) -> O + SendSync + 'static,
+    ) -> Map1<I, O> {
+        self.write(|dag| dag.map(input, f))
+    }
+
+    pub fn map_with_cutoff<I: Clone + 'static, O: SendSync + 'static>(
+        &self,
+        input: impl Observable<I>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(I) -> O + SendSync + 'static,
+    ) -> Map1<I, O> {
+        self.write(|dag| dag.map_with_cutoff(input, cutoff, f))
+    }
+
+    pub fn map2<I1: Clone + 'static, I2: Clone + 'static, O: PartialEq + SendSync + 'static>(
+        &self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        f: impl Fn(I1, I2) -> O + SendSync + 'static,
+    ) -> Map2<I1, I2, O> {
+        self.write(|dag| dag.map2(input1, input2, f))
+    }
+
+    pub fn map2_with_cutoff<I1: Clone + 'static, I2: Clone + 'static, O: SendSync + 'static>(
+        &self,
+        input1: impl Observable<I1>,
+        input2: impl Observable<I2>,
+        cutoff: Cutoff<O>,
+        f: impl Fn(I1, I2) -> O + SendSync + 'static,
+    ) -> Map2<I1, I2, O> {
+        self.write(|dag| dag.map2_with_cutoff(input1, input2, cutoff, f))
+    }
+
+    pub fn bind<I: Clone + 'static, O: 'static>(
+        &self,
+        input: impl Observable<I>,
+        f: impl Fn(I, &mut Scope) -> Input<O> + SendSync + 'static,
+    ) -> Bind1<I, O> {
+        self.write(|dag| dag.bind(input, f))
+    }
+
+    pub fn observe<T: Clone + SendSync + 'static>(&self, input: impl Observable<T>) -> Observer<T> {
+        self.write(|dag| dag.observe(input))
+    }
+
+    /// Sets `var` to `value` at the start of the next stabilization. A set made while a
+    /// stabilization runs waits for the one after it.
+    pub fn set<T: Clone + SendSync + 'static>(&self, var: Var<T>, value: T) {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Box::new(move |dag: &mut Incrementars| var.set(dag, value)));
+    }
+
+    /// Applies pending sets and stabilizes, holding readers off until both are done.
+    pub fn stablize(&self) {
+        if let Err(err) = self.try_stablize() {
+            panic!("{}", err);
+        }
+    }
+
+    /// Like [`SharedIncrementars::stablize`], reporting errors like [`Incrementars::try_stablize`].
+    /// Also fails with [`IncrError::Reentrant`] if called from inside a stabilization or build
+    /// running on this thread.
+    pub fn try_stablize(&self) -> Result<(), IncrError> {
+        let mut exclusive = self.exclusive()?;
+        let dag = &mut exclusive.dag;
+        let pending =
+            std::mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner));
+        pending.into_iter().for_each(|set| (set)(dag));
+        dag.try_stablize()
+    }
+}
@@ -1,28 +1,120 @@
+use std::any::Any;
+
+use super::error::IncrError;
+use super::id::{Input, NodeId};
+use super::scope::Scope;
+use super::Incrementars;
+
+/// Bound on everything the graph stores: node values and user functions. It means `Send + Sync`
+/// with the `sync` feature enabled, and nothing otherwise.
+#[cfg(feature = "sync")]
+pub trait SendSync: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> SendSync for T {}
+
+#[cfg(not(feature = "sync"))]
+pub trait SendSync {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> SendSync for T {}
+
+/// Trait object type for a stored user function, `Send + Sync` with the `sync` feature enabled.
+#[cfg(feature = "sync")]
+macro_rules! sync_dyn {
+    ($($bound:tt)+) => { dyn $($bound)+ + Send + Sync };
+}
+#[cfg(not(feature = "sync"))]
+macro_rules! sync_dyn {
+    ($($bound:tt)+) => { dyn $($bound)+ };
+}
+pub(crate) use sync_dyn;
+
+/// What a recomputed node asks of the graph. Nodes return `None` when there is nothing to do.
 pub enum StablizationCallback {
     ValueChanged,
-    DependenciesUpdated { from: Vec<usize>, to: Vec<usize> },
+    /// The node now reads from `to` instead of `from`. Its value changed as well.
+    DependenciesUpdated {
+        from: Vec<NodeId>,
+        to: Vec<NodeId>,
+    },
+    NotifyObservers,
+    /// The node could not compute a value, either on its own or because an input failed.
+    Failed(IncrError),
 }
 
-pub trait Node {
-    fn id(&self) -> usize;
-    fn stablize(&mut self) -> Vec<StablizationCallback>;
-    fn depth(&self) -> i32;
-    fn adjust_depth(&mut self, new_depth: i32);
+/// Where a node keeps its current value.
+pub enum NodeValue<'a> {
+    /// The node owns its value.
+    Here(&'a dyn Any),
+    /// The node shows the value of another node, as binds do with the node they picked.
+    At(NodeId),
 }
 
-pub trait Observable<T> {
-    fn id(&self) -> usize;
-    fn observe(&self) -> T;
-    fn depth(&self) -> i32;
+pub trait Node: Any + SendSync {
+    fn stablize(&mut self, scope: &mut Scope) -> Option<StablizationCallback>;
+    /// Ids of the nodes this node currently reads from.
+    fn inputs(&self) -> Vec<NodeId>;
+    /// Whether the node currently reads from exactly `inputs`, in order. Binds answer without
+    /// building the list.
+    fn reads_from(&self, inputs: &[NodeId]) -> bool {
+        self.inputs() == inputs
+    }
+    /// The node's current value. The graph never reads failed nodes, and only those can be
+    /// missing a value, so implementations may unwrap it.
+    fn value(&self) -> NodeValue<'_>;
+    /// Whether recomputing the node can add, remove or rewire nodes, as binds do.
+    fn changes_graph(&self) -> bool {
+        false
+    }
+    /// Whether the node holds a change the graph has yet to propagate, as a set var does.
+    fn is_dirty(&self) -> bool {
+        false
+    }
+    /// Binds forget the node they just picked, when the graph refuses to read from it.
+    fn drop_pick(&mut self) {}
+    /// Observers hand themselves out here, so the graph can run their handlers.
+    fn as_observer(&mut self) -> Option<&mut dyn Notify> {
+        None
+    }
 }
 
-impl<T> PartialEq for dyn Observable<T> {
-    fn eq(&self, other: &Self) -> bool {
-        self.id() == other.id()
+/// A typed handle to a node whose value is a `T`. Handles are plain ids; reading a value goes
+/// through the graph that owns the node.
+pub trait Observable<T: 'static> {
+    fn id(&self) -> NodeId;
+
+    fn as_input(&self) -> Input<T> {
+        Input::new(self.id())
+    }
+
+    fn observe(&self, dag: &Incrementars) -> T
+    where
+        T: Clone,
+    {
+        self.with(dag, T::clone)
+    }
+
+    /// Like [`Observable::observe`], but returns an error instead of panicking if the node has
+    /// been removed from the graph, has failed, or is out of date because nothing observes it.
+    fn try_observe(&self, dag: &Incrementars) -> Result<T, IncrError>
+    where
+        T: Clone,
+    {
+        dag.try_read(self.id()).cloned()
+    }
+
+    /// Runs `f` on a reference to the current value and returns what it returns. Reading a large
+    /// value this way avoids the clone that [`Observable::observe`] makes.
+    fn with<R>(&self, dag: &Incrementars, f: impl FnOnce(&T) -> R) -> R {
+        (f)(dag.read(self.id()))
+    }
+
+    fn height(&self, dag: &Incrementars) -> usize {
+        dag.height(self.id())
     }
 }
 
-pub trait MaybeDirty {
-    fn id(&self) -> usize;
-    fn is_dirty(&self) -> bool;
+/// Something that runs user handlers once a stabilization has finished.
+pub trait Notify {
+    fn notify(&mut self, dag: &Incrementars);
+    fn invalidate(&mut self, dag: &Incrementars);
 }
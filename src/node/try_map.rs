@@ -0,0 +1,76 @@
+use std::marker::PhantomData;
+
+use super::cutoff::Cutoff;
+use super::error::IncrError;
+use super::id::{Input, NodeId};
+use super::scope::Scope;
+use super::traits::{sync_dyn, Node, NodeValue, Observable, SendSync, StablizationCallback};
+use super::Incrementars;
+
+type TryMapFn<I, O> = Box<sync_dyn!(Fn(&I) -> Result<O, String>)>;
+
+pub struct _TryMap1<I, O> {
+    pub id: NodeId,
+    // last value `f` produced, `None` until it first succeeds.
+    pub value: Option<O>,
+    pub input: Input<I>,
+    pub f: TryMapFn<I, O>,
+    pub cutoff: Cutoff<O>,
+}
+
+impl<I: 'static, O: SendSync + 'static> Node for _TryMap1<I, O> {
+    fn stablize(&mut self, scope: &mut Scope) -> Option<StablizationCallback> {
+        match (self.f)(scope.read(self.input.id())) {
+            Ok(new_value) => {
+                if self.cutoff.should_cutoff_from(&self.value, &new_value) {
+                    return None;
+                }
+                self.value = Some(new_value);
+                Some(StablizationCallback::ValueChanged)
+            }
+            Err(message) => Some(StablizationCallback::Failed(IncrError::Failed {
+                node: self.id,
+                message,
+            })),
+        }
+    }
+    fn inputs(&self) -> Vec<NodeId> {
+        vec![self.input.id()]
+    }
+    fn value(&self) -> NodeValue<'_> {
+        NodeValue::Here(self.value.as_ref().unwrap())
+    }
+}
+
+pub struct TryMap1<I, O> {
+    id: NodeId,
+    _types: PhantomData<fn(I) -> O>,
+}
+
+impl<I: 'static, O: 'static> Observable<O> for TryMap1<I, O> {
+    fn id(&self) -> NodeId {
+        self.id
+    }
+}
+
+impl<I, O> Clone for TryMap1<I, O> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I, O> Copy for TryMap1<I, O> {}
+
+impl<I: 'static, O: SendSync + 'static> TryMap1<I, O> {
+    pub(super) fn new(id: NodeId) -> Self {
+        Self {
+            id,
+            _types: PhantomData,
+        }
+    }
+
+    /// Replaces the cutoff used to decide whether a recomputed value propagates to dependents.
+    pub fn set_cutoff(&self, dag: &mut Incrementars, cutoff: Cutoff<O>) {
+        dag.node_mut::<_TryMap1<I, O>>(self.id).cutoff = cutoff;
+    }
+}
@@ -0,0 +1,77 @@
+use std::marker::PhantomData;
+
+use super::cutoff::Cutoff;
+use super::error::IncrError;
+use super::id::{Input, NodeId};
+use super::scope::Scope;
+use super::traits::{sync_dyn, Node, NodeValue, Observable, SendSync, StablizationCallback};
+use super::Incrementars;
+
+type TryMap2Fn<I1, I2, O> = Box<sync_dyn!(Fn(&I1, &I2) -> Result<O, String>)>;
+
+pub struct _TryMap2<I1, I2, O> {
+    pub id: NodeId,
+    // last value `f` produced, `None` until it first succeeds.
+    pub value: Option<O>,
+    pub input1: Input<I1>,
+    pub input2: Input<I2>,
+    pub f: TryMap2Fn<I1, I2, O>,
+    pub cutoff: Cutoff<O>,
+}
+
+impl<I1: 'static, I2: 'static, O: SendSync + 'static> Node for _TryMap2<I1, I2, O> {
+    fn stablize(&mut self, scope: &mut Scope) -> Option<StablizationCallback> {
+        match (self.f)(scope.read(self.input1.id()), scope.read(self.input2.id())) {
+            Ok(new_value) => {
+                if self.cutoff.should_cutoff_from(&self.value, &new_value) {
+                    return None;
+                }
+                self.value = Some(new_value);
+                Some(StablizationCallback::ValueChanged)
+            }
+            Err(message) => Some(StablizationCallback::Failed(IncrError::Failed {
+                node: self.id,
+                message,
+            })),
+        }
+    }
+    fn inputs(&self) -> Vec<NodeId> {
+        vec![self.input1.id(), self.input2.id()]
+    }
+    fn value(&self) -> NodeValue<'_> {
+        NodeValue::Here(self.value.as_ref().unwrap())
+    }
+}
+
+pub struct TryMap2<I1, I2, O> {
+    id: NodeId,
+    _types: PhantomData<fn(I1, I2) -> O>,
+}
+
+impl<I1: 'static, I2: 'static, O: 'static> Observable<O> for TryMap2<I1, I2, O> {
+    fn id(&self) -> NodeId {
+        self.id
+    }
+}
+
+impl<I1, I2, O> Clone for TryMap2<I1, I2, O> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I1, I2, O> Copy for TryMap2<I1, I2, O> {}
+
+impl<I1: 'static, I2: 'static, O: SendSync + 'static> TryMap2<I1, I2, O> {
+    pub(super) fn new(id: NodeId) -> Self {
+        Self {
+            id,
+            _types: PhantomData,
+        }
+    }
+
+    /// Replaces the cutoff used to decide whether a recomputed value propagates to dependents.
+    pub fn set_cutoff(&self, dag: &mut Incrementars, cutoff: Cutoff<O>) {
+        dag.node_mut::<_TryMap2<I1, I2, O>>(self.id).cutoff = cutoff;
+    }
+}
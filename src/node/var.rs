@@ -1,87 +1,144 @@
-use std::{cell::RefCell, rc::Rc};
+use std::marker::PhantomData;
 
-use super::traits::{MaybeDirty, Node, Observable, StablizationCallback};
-use std::ops::Deref;
+use super::cutoff::Cutoff;
+use super::id::NodeId;
+use super::scope::Scope;
+use super::traits::{Node, NodeValue, Observable, SendSync, StablizationCallback};
+use super::Incrementars;
 
 /// Internal representation of a Var node.
 pub struct _Var<T> {
-    id: usize,
-    depth: i32,
     value: T,
     dirty: bool,
+    // whether the id is already in the graph's dirty list.
+    queued: bool,
+    cutoff: Cutoff<T>,
+    // value as of the last stabilization, kept only while a cutoff needs it for comparison.
+    stable: Option<T>,
 }
 
-impl<T> Node for _Var<T> {
-    fn id(&self) -> usize {
-        self.id
+impl<T: SendSync + 'static> Node for _Var<T> {
+    fn stablize(&mut self, _: &mut Scope) -> Option<StablizationCallback> {
+        let dirty = std::mem::take(&mut self.dirty);
+        self.queued = false;
+        let stable = self.stable.take();
+        if dirty {
+            return Some(StablizationCallback::ValueChanged);
+        }
+        // the cutoff deemed every set unchanged, so dependents still hold the stabilized value.
+        // Show that one as well.
+        if let Some(stable) = stable {
+            self.value = stable;
+        }
+        None
     }
-    fn depth(&self) -> i32 {
-        self.depth
+    fn inputs(&self) -> Vec<NodeId> {
+        vec![]
     }
-    fn stablize(&mut self) -> Vec<StablizationCallback> {
-        self.dirty = false;
-        vec![StablizationCallback::ValueChanged]
+    fn value(&self) -> NodeValue<'_> {
+        NodeValue::Here(&self.value)
     }
-    fn adjust_depth(&mut self, _: i32) {
-        panic!("Var height should not change");
+    fn is_dirty(&self) -> bool {
+        self.dirty
     }
 }
 
 impl<T> _Var<T> {
-    pub fn new(id: usize, depth: i32, value: T) -> Self {
+    pub fn new(value: T) -> Self {
         Self {
-            id,
-            depth,
             value,
             dirty: false,
+            queued: false,
+            cutoff: Cutoff::never(),
+            stable: None,
+        }
+    }
+
+    fn replace(&mut self, value: T) -> bool {
+        let old = std::mem::replace(&mut self.value, value);
+        if !self.cutoff.is_never() && self.stable.is_none() {
+            self.stable = Some(old);
         }
+        self.mark_dirty()
+    }
+
+    fn update(&mut self, f: impl FnOnce(&mut T)) -> bool
+    where
+        T: Clone,
+    {
+        if !self.cutoff.is_never() && self.stable.is_none() {
+            self.stable = Some(self.value.clone());
+        }
+        (f)(&mut self.value);
+        self.mark_dirty()
+    }
+
+    /// Returns whether the var needs to be queued for the next stabilization. A set the cutoff
+    /// deems unchanged still gets queued, so stabilizing can put the stabilized value back.
+    fn mark_dirty(&mut self) -> bool {
+        self.dirty = match &self.stable {
+            Some(stable) => !self.cutoff.should_cutoff(stable, &self.value),
+            None => true,
+        };
+        !std::mem::replace(&mut self.queued, true)
     }
 }
+
 /// A variable node.
 pub struct Var<T> {
-    pub node: Rc<RefCell<_Var<T>>>,
+    id: NodeId,
+    _value: PhantomData<fn() -> T>,
 }
 
 impl<T> Clone for Var<T> {
     fn clone(&self) -> Self {
-        Self {
-            node: self.node.clone(),
-        }
+        *self
     }
 }
 
-impl<T> Var<T> {
-    pub fn set(&self, value: T) {
-        let mut internal = self.node.deref().borrow_mut();
-        internal.value = value;
-        internal.dirty = true;
-    }
-}
+impl<T> Copy for Var<T> {}
 
-impl<T> MaybeDirty for Var<T> {
-    fn id(&self) -> usize {
-        self.node.deref().borrow().id
+impl<T: SendSync + 'static> Var<T> {
+    pub(super) fn new(id: NodeId) -> Self {
+        Self {
+            id,
+            _value: PhantomData,
+        }
     }
-    fn is_dirty(&self) -> bool {
-        self.node.deref().borrow().dirty
+
+    pub fn set(&self, dag: &mut Incrementars, value: T) {
+        if dag.node_mut::<_Var<T>>(self.id).replace(value) {
+            dag.dirty.push(self.id);
+        }
     }
-}
 
-impl<T: Clone> Observable<T> for Var<T> {
-    fn id(&self) -> usize {
-        self.node.deref().borrow().id
+    /// Mutates the value in place and marks the var dirty, without moving it. With a cutoff set,
+    /// the first update after a stabilization clones the value to compare against.
+    pub fn update(&self, dag: &mut Incrementars, f: impl FnOnce(&mut T))
+    where
+        T: Clone,
+    {
+        if dag.node_mut::<_Var<T>>(self.id).update(f) {
+            dag.dirty.push(self.id);
+        }
     }
-    fn observe(&self) -> T {
-        let borrowed = self.node.deref().borrow();
-        borrowed.value.clone()
+
+    /// Sets the policy deciding whether a new value differs from the last stabilized one. Sets
+    /// that the cutoff deems unchanged leave the var clean, so nothing downstream recomputes, and
+    /// the next stabilization puts the last stabilized value back. Vars default to
+    /// [`Cutoff::never`], where every set propagates.
+    pub fn set_cutoff(&self, dag: &mut Incrementars, cutoff: Cutoff<T>) {
+        dag.node_mut::<_Var<T>>(self.id).cutoff = cutoff;
     }
-    fn depth(&self) -> i32 {
-        self.node.deref().borrow().depth
+
+    /// Whether the var was set to a new value since the last stabilization.
+    pub fn is_dirty(&self, dag: &Incrementars) -> bool {
+        dag.node_ref::<_Var<T>>(self.id).dirty
     }
 }
 
-impl<T> Var<T> {
-    pub fn as_input(&self) -> Box<Var<T>> {
-        Box::new(self.clone())
+impl<T: 'static> Observable<T> for Var<T> {
+    fn id(&self) -> NodeId {
+        self.id
     }
 }
@@ -0,0 +1,67 @@
+//! Lives in its own test binary, because it replaces the global allocator to count allocations.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use incrementars::prelude::{Incrementars, Observable};
+
+// counts per thread, so tests running alongside this one do not interfere.
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_steady_state_stablize_does_not_allocate() {
+    // the `iter` scenario from `src/bin/main.rs`, plus a bind forwarding one of its leaves.
+    let mut dag = Incrementars::new();
+    let var = dag.var(0);
+    let map0 = dag.map(var, |x| x);
+    let mut queue = vec![map0.as_input()];
+    for _ in 0..500 {
+        let head = queue.pop().unwrap();
+        let out1 = dag.map(head, |x| x);
+        let out2 = dag.map(head, |x| x);
+        queue.push(out1.as_input());
+        queue.push(out2.as_input());
+    }
+    queue.iter().for_each(|leaf| {
+        dag.observe(*leaf);
+    });
+    let use_first = dag.var(true);
+    let (first, last) = (queue[0], queue[queue.len() - 1]);
+    let picked = dag.bind(use_first, move |use_first, _| match use_first {
+        true => first,
+        false => last,
+    });
+    let above = dag.map(picked, |x| x + 1);
+    dag.observe(above);
+    for i in 0..3 {
+        var.set(&mut dag, i);
+        dag.stablize();
+    }
+
+    let before = ALLOCATIONS.with(Cell::get);
+    for i in 0..1_000 {
+        var.set(&mut dag, i);
+        dag.stablize();
+    }
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+    assert_eq!(queue[0].observe(&dag), 999);
+    assert_eq!(above.observe(&dag), 1_000);
+}